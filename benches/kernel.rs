@@ -0,0 +1,66 @@
+//! Generations per second of the bit packed kernel for several board
+//! sizes, soup densities and numbers of worker threads.
+//!
+//! Run with `cargo bench --no-default-features --bench kernel`.
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+
+use rusty_life::grid::{Grid, Topology};
+use rusty_life::rule::Rule;
+use rusty_life::soup::Soup;
+
+/// Every iteration steps a fresh copy of the soup, which would otherwise
+/// settle into ash whose idle tiles are skipped.
+fn soup_grid(size : u32, density : f64) -> Grid {
+    let mut grid = Grid::new((size, size), Rule::conway(), Topology::Torus);
+    let soup = Soup { density, ..Soup::new(42) };
+    soup.fill(&mut grid, (size as usize, size as usize));
+    grid
+}
+
+/// Powers of two up to the number of cores, and the number of cores.
+fn thread_counts() -> Vec<usize> {
+    let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let mut counts = (0..).map(|i| 1 << i).take_while(|n| *n < cores).collect::<Vec<_>>();
+    counts.push(cores);
+    counts
+}
+
+fn sizes_and_densities(c : &mut Criterion) {
+    let mut group = c.benchmark_group("run_lifecycle");
+    group.sample_size(20);
+    group.throughput(Throughput::Elements(1));
+    for &size in &[512, 1024, 2048, 4096] {
+        for &density in &[0.5, 0.2, 0.05] {
+            let soup = soup_grid(size, density);
+            let id = BenchmarkId::new(format!("{}x{}", size, size), density);
+            group.bench_function(id, |b| {
+                b.iter_batched(|| soup.clone(), |mut grid| {
+                    grid.run_lifecycle();
+                    grid
+                }, BatchSize::LargeInput)
+            });
+        }
+    }
+    group.finish();
+}
+
+fn threads(c : &mut Criterion) {
+    let mut group = c.benchmark_group("run_lifecycle_threads");
+    group.sample_size(20);
+    group.throughput(Throughput::Elements(1));
+    for threads in thread_counts() {
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+        let soup = soup_grid(4096, 0.5);
+        group.bench_with_input(BenchmarkId::new("4096x4096", threads), &threads, |b, _| {
+            b.iter_batched(|| soup.clone(), |mut grid| {
+                pool.install(|| grid.run_lifecycle());
+                grid
+            }, BatchSize::LargeInput)
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, sizes_and_densities, threads);
+criterion_main!(benches);
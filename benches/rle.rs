@@ -0,0 +1,43 @@
+//! Parse throughput of `read_rle` on large RLE files written from random
+//! soups.
+//!
+//! Run with `cargo bench --no-default-features --bench rle`.
+
+use std::path::PathBuf;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use rusty_life::engine::Rect;
+use rusty_life::file_reader::read_rle;
+use rusty_life::file_writer::{save_rle, RleComments};
+use rusty_life::grid::{Grid, Topology};
+use rusty_life::rule::Rule;
+use rusty_life::soup::Soup;
+
+/// Writes a `size` x `size` soup to a file in the temporary directory.
+fn soup_file(size : u32, density : f64) -> PathBuf {
+    let mut grid = Grid::new((size, size), Rule::conway(), Topology::Torus);
+    let soup = Soup { density, ..Soup::new(42) };
+    soup.fill(&mut grid, (size as usize, size as usize));
+
+    let path = std::env::temp_dir().join(format!("rusty_life_bench_{}_{}.rle", size, density));
+    let area = Rect { col : 0, row : 0, width : size as i64, height : size as i64 };
+    save_rle(&grid, area, &RleComments::default(), &path).unwrap();
+    path
+}
+
+fn read(c : &mut Criterion) {
+    let mut group = c.benchmark_group("read_rle");
+    group.sample_size(10);
+    for &(size, density) in &[(1024, 0.5), (4096, 0.5), (4096, 0.05)] {
+        let path = soup_file(size, density);
+        group.throughput(Throughput::Bytes(std::fs::metadata(&path).unwrap().len()));
+        let id = BenchmarkId::new(format!("{}x{}", size, size), density);
+        group.bench_function(id, |b| b.iter(|| read_rle(&path).unwrap()));
+        std::fs::remove_file(&path).unwrap();
+    }
+    group.finish();
+}
+
+criterion_group!(benches, read);
+criterion_main!(benches);
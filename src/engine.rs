@@ -0,0 +1,145 @@
+//! Interface shared by the algorithms stepping a pattern.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::grid::Region;
+use crate::hashlife;
+use crate::rule::Rule;
+
+/// Largest exponent of a step of any engine, see `Engine::step`. HashLife
+/// advances its root by at most a quarter of its size, so larger steps
+/// would need a universe beyond `i64` coordinates.
+pub const MAX_STEP_EXPONENT : u32 = hashlife::MAX_LEVEL - 3;
+
+/// Largest exponent of a step of the engines computing one generation
+/// after the other, larger steps would keep them busy for hours.
+pub const MAX_ITERATED_STEP_EXPONENT : u32 = 16;
+
+/// Rectangular area of the universe given by its top left cell and size.
+/// Unlike a `Region` it may lie anywhere, also at negative coordinates.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Rect {
+    pub col : i64,
+    pub row : i64,
+    pub width : i64,
+    pub height : i64,
+}
+
+impl From<Region> for Rect {
+    fn from(region : Region) -> Rect {
+        Rect {
+            col : region.col as i64,
+            row : region.row as i64,
+            width : region.width as i64,
+            height : region.height as i64,
+        }
+    }
+}
+
+/// Algorithm stepping a pattern, either on a fixed board or in an
+/// unbounded universe. Cells outside of a bounded board are dead and
+/// setting them has no effect.
+pub trait Engine : Send {
+    fn rule(&self) -> Rule;
+
+    /// Switches the rule, fails if the engine can't simulate it.
+    fn set_rule(&mut self, rule : Rule) -> Result<(), String>;
+
+    fn get_cell(&self, col : i64, row : i64) -> bool;
+
+    fn set_cell(&mut self, col : i64, row : i64, value : bool);
+
+    /// State of a cell, see `Rule`: 0 if dead, 1 if alive and 2 or more if
+    /// dying or in another state of a rule table.
+    fn get_state(&self, col : i64, row : i64) -> u8 {
+        self.get_cell(col, row) as u8
+    }
+
+    /// Sets the state of a cell, see `get_state`. Engines that only know
+    /// dead and alive cells ignore other states.
+    fn set_state(&mut self, col : i64, row : i64, state : u8) {
+        if state < 2 {
+            self.set_cell(col, row, state == 1);
+        }
+    }
+
+    /// Writes the state of every cell of `area` to `states`, row by row.
+    fn fill_states(&self, area : Rect, states : &mut [u8]) {
+        for (i, state) in states.iter_mut().enumerate().take((area.width * area.height) as usize) {
+            let (col, row) = (i as i64 % area.width, i as i64 / area.width);
+            *state = self.get_state(area.col + col, area.row + row);
+        }
+    }
+
+    /// Number of alive cells.
+    fn population(&self) -> u64;
+
+    /// Smallest area containing all alive cells, `None` if there are none.
+    fn bounding_box(&self) -> Option<Rect>;
+
+    /// Advances the pattern by `2^exponent` generations, fails without
+    /// changing it if `exponent` exceeds `max_step_exponent()` or the
+    /// pattern grows beyond the universe of the engine.
+    fn step(&mut self, exponent : u32) -> Result<(), String>;
+
+    /// Largest exponent `step` accepts, at most `MAX_STEP_EXPONENT`.
+    fn max_step_exponent(&self) -> u32;
+
+    /// Hash of the alive cells and their positions. Equal patterns at the
+    /// same position have the same hash.
+    fn state_hash(&self) -> u64;
+
+    /// Writes the cells of `area` to `words` packed like the cells of a
+    /// `Grid` with the size of `area`: every row starts with a new word and
+    /// the most significant bit of a word is its leftmost cell.
+    fn fill_words(&self, area : Rect, words : &mut [u16]);
+
+    /// Whether a step may be split into tasks run on the threads of the
+    /// current rayon pool, otherwise it runs on the calling thread alone.
+    /// Engines that don't use threads ignore it.
+    fn set_parallel(&mut self, _parallel : bool) {}
+}
+
+/// Fails if `exponent` exceeds `max_exponent`.
+pub(crate) fn check_step_exponent(exponent : u32, max_exponent : u32) -> Result<(), String> {
+    if exponent > max_exponent {
+        return Err(format!("Steps of 2^{} generations exceed the largest ones of 2^{}", exponent, max_exponent));
+    }
+    Ok(())
+}
+
+/// Algorithms available to a `Simulation`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EngineKind {
+    /// Bit packed board stepped one generation at a time, see `grid::Grid`
+    Grid,
+    /// Memoized quadtree of the unbounded plane, see `hashlife::HashLife`
+    HashLife,
+    /// Unbounded plane of bit tiles allocated on demand, see `sparse::SparseGrid`
+    Sparse,
+}
+
+impl FromStr for EngineKind {
+    type Err = String;
+
+    fn from_str(s : &str) -> Result<EngineKind, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "grid" => Ok(EngineKind::Grid),
+            "hashlife" => Ok(EngineKind::HashLife),
+            "sparse" => Ok(EngineKind::Sparse),
+            _ => Err(format!("expected \"grid\", \"hashlife\" or \"sparse\", found \"{}\"", s)),
+        }
+    }
+}
+
+impl fmt::Display for EngineKind {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            EngineKind::Grid => "grid",
+            EngineKind::HashLife => "hashlife",
+            EngineKind::Sparse => "sparse",
+        };
+        write!(f, "{}", name)
+    }
+}
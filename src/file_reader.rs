@@ -0,0 +1,248 @@
+//! Reading patterns in the RLE format.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead};
+use std::path::Path;
+
+use crate::rule::Rule;
+
+#[derive(Default)]
+pub struct Pattern {
+    /// Cells that aren't dead
+    pub pattern : std::vec::Vec<(usize, usize)>,
+    /// State of every cell of `pattern`, 1 unless the pattern is written
+    /// with the letters of multi-state RLE
+    pub states : Vec<u8>,
+    pub width : usize,
+    pub height : usize,
+    pub name : String,
+    pub author : String,
+    pub comments : Vec<String>,
+    pub rule : Option<Rule>,
+}
+
+/// Errors reported by `read_rle`. Lines and columns are 1-based.
+#[derive(Debug)]
+pub enum RleError {
+    Io(io::Error),
+    BadHeader { line : usize, column : usize, message : String },
+    BadRunCount { line : usize, column : usize, count : String },
+    UnexpectedChar { line : usize, column : usize, found : char },
+    PatternExceedsDeclaredSize { line : usize, column : usize, width : usize, height : usize },
+}
+
+impl fmt::Display for RleError {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RleError::Io(err) => write!(f, "{}", err),
+            RleError::BadHeader { line, column, message } =>
+                write!(f, "line {}, column {}: bad header: {}", line, column, message),
+            RleError::BadRunCount { line, column, count } =>
+                write!(f, "line {}, column {}: invalid run count \"{}\"", line, column, count),
+            RleError::UnexpectedChar { line, column, found } =>
+                write!(f, "line {}, column {}: unexpected character '{}'", line, column, found),
+            RleError::PatternExceedsDeclaredSize { line, column, width, height } =>
+                write!(f, "line {}, column {}: pattern exceeds declared size {}x{}",
+                       line, column, width, height),
+        }
+    }
+}
+
+impl std::error::Error for RleError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RleError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for RleError {
+    fn from(err : io::Error) -> RleError {
+        RleError::Io(err)
+    }
+}
+
+pub fn read_rle<P>(filepath : P) -> Result<Pattern, RleError>
+    where P: AsRef<Path> {
+    let file = File::open(filepath)?;
+    parse_rle(io::BufReader::new(file))
+}
+
+/// Parses the header line "x = m, y = n, rule = abc" into `p`.
+fn parse_header(l : &str, line : usize, p : &mut Pattern) -> Result<(), RleError> {
+    let bad = |column : usize, message : String| RleError::BadHeader { line, column, message };
+
+    let mut width = None;
+    let mut height = None;
+    let mut offset = 0;
+
+    while offset < l.len() {
+        let field_end = l[offset..].find(',').map_or(l.len(), |idx| offset + idx);
+        let field = &l[offset..field_end];
+        let column = offset + (field.len() - field.trim_start().len()) + 1;
+
+        let eq_idx = match field.find('=') {
+            Some(idx) => idx,
+            None => return Err(bad(column, format!("expected \"key = value\", found \"{}\"", field.trim()))),
+        };
+        let value = field[eq_idx + 1..].trim();
+        let value_column = offset + eq_idx + 1 +
+                           (field[eq_idx + 1..].len() - field[eq_idx + 1..].trim_start().len()) + 1;
+
+        match field[..eq_idx].trim() {
+            "x" => width = Some(value.parse::<usize>()
+                                     .map_err(|_| bad(value_column, format!("invalid width \"{}\"", value)))?),
+            "y" => height = Some(value.parse::<usize>()
+                                      .map_err(|_| bad(value_column, format!("invalid height \"{}\"", value)))?),
+            "rule" => {
+                // The rule is the last field and may contain commas itself
+                // (e.g. "rule = B3/S23:T100,100"), so it takes the rest of the line.
+                let rule = l[offset + eq_idx + 1..].trim();
+                // Drop Golly's bounded grid suffix ":T100,100"
+                let rule = rule.split(':').next().unwrap_or("");
+                p.rule = Some(Rule::from_pattern(rule).map_err(|err| bad(value_column, err))?);
+                break;
+            },
+            key => return Err(bad(column, format!("unknown field \"{}\"", key))),
+        }
+
+        offset = field_end + 1;
+    }
+
+    match (width, height) {
+        (Some(w), Some(h)) => {
+            p.width = w;
+            p.height = h;
+            Ok(())
+        },
+        (None, _) => Err(bad(1, String::from("missing \"x = \" field"))),
+        (_, None) => Err(bad(1, String::from("missing \"y = \" field"))),
+    }
+}
+
+/// Parses an RLE pattern from any buffered reader. Besides 'b' and 'o'
+/// the cells may be given in multi-state RLE, where '.' is a dead cell,
+/// 'A' to 'X' are the states 1 to 24 and the letters after a prefix 'p'
+/// to 'y' the next 24 states each.
+pub fn parse_rle<R>(reader : R) -> Result<Pattern, RleError>
+    where R: BufRead {
+    let mut p = Pattern::default();
+
+    let mut header_finished = false;
+    let mut row = 0;
+    let mut col = 0;
+    let mut line = 0;
+    // Pending run count, the line and column it started at and its digits
+    let mut run : Option<(usize, usize, usize)> = None;
+    let mut run_str = String::new();
+
+    'scan_lines: for l in reader.lines() {
+        let l = l?;
+        line += 1;
+
+        if !header_finished {
+            let trimmed = l.trim();
+            match trimmed.get(..2) {
+                Some("#O") => p.author = String::from(trimmed[2..].trim()),
+                Some("#N") => p.name = String::from(trimmed[2..].trim()),
+                Some("#C") | Some("#c") => p.comments.push(String::from(trimmed[2..].trim())),
+                _ if trimmed.is_empty() || trimmed.starts_with('#') => (),
+                _ if trimmed.starts_with('x') => {
+                    parse_header(&l, line, &mut p)?;
+                    header_finished = true;
+                },
+                _ => {
+                    let column = l.len() - l.trim_start().len() + 1;
+                    return Err(RleError::BadHeader {
+                        line,
+                        column,
+                        message : String::from("expected header line \"x = m, y = n\""),
+                    });
+                },
+            }
+            continue;
+        }
+
+        let mut chars = l.chars().enumerate().peekable();
+        while let Some((idx, c)) = chars.next() {
+            let column = idx + 1;
+            if let Some(digit) = c.to_digit(10) {
+                let (start_line, start_column, num) = run.unwrap_or((line, column, 0));
+                run_str.push(c);
+                match num.checked_mul(10).and_then(|n| n.checked_add(digit as usize)) {
+                    Some(n) => run = Some((start_line, start_column, n)),
+                    None => return Err(RleError::BadRunCount {
+                        line : start_line,
+                        column : start_column,
+                        count : run_str,
+                    }),
+                }
+                continue;
+            }
+            if c.is_whitespace() {
+                continue;
+            }
+
+            let num = match run.take() {
+                Some((start_line, start_column, 0)) => return Err(RleError::BadRunCount {
+                    line : start_line,
+                    column : start_column,
+                    count : run_str,
+                }),
+                Some((_, _, n)) => n,
+                None => 1,
+            };
+            run_str.clear();
+
+            let state = match c {
+                'o' => 1,
+                'A'..='X' => c as u32 - 'A' as u32 + 1,
+                'p'..='y' => match chars.next_if(|(_, letter)| ('A'..='X').contains(letter)) {
+                    Some((_, letter)) => 24 * (c as u32 - 'p' as u32 + 1) + letter as u32 - 'A' as u32 + 1,
+                    None => return Err(RleError::UnexpectedChar { line, column, found : c }),
+                },
+                _ => 0,
+            };
+            if state > u8::MAX as u32 {
+                return Err(RleError::UnexpectedChar { line, column, found : c });
+            }
+
+            match c {
+                '$' => {
+                    row += num;
+                    col = 0;
+                },
+                'b' | '.' => col += num,
+                _ if state != 0 => {
+                    if row >= p.height || col + num > p.width {
+                        return Err(RleError::PatternExceedsDeclaredSize {
+                            line,
+                            column,
+                            width : p.width,
+                            height : p.height,
+                        });
+                    }
+                    for k in 0..num {
+                        p.pattern.push((col + k, row));
+                        p.states.push(state as u8);
+                    }
+                    col += num;
+                },
+                '!' => break 'scan_lines,
+                found => return Err(RleError::UnexpectedChar { line, column, found }),
+            }
+        }
+    }
+
+    if !header_finished {
+        return Err(RleError::BadHeader {
+            line : line + 1,
+            column : 1,
+            message : String::from("missing header line \"x = m, y = n\""),
+        });
+    }
+
+    Ok(p)
+}
@@ -0,0 +1,173 @@
+//! Writing boards in the RLE format.
+
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::engine::{Engine, Rect};
+
+/// Maximum length of a pattern line as recommended by the RLE format.
+const MAX_LINE_LENGTH : usize = 70;
+
+/// Comment lines written in front of the RLE header.
+#[derive(Default)]
+pub struct RleComments {
+    pub name : String,
+    pub author : String,
+    pub comments : Vec<String>,
+}
+
+/// Buffers run length encoded tokens and wraps lines before they exceed
+/// `MAX_LINE_LENGTH` without splitting a run count from its tag.
+struct RunWriter<'a, W : Write> {
+    out : &'a mut W,
+    line_length : usize,
+    // Line ends and dead cells are only written once the next cell that
+    // isn't dead shows up, so empty rows collapse into a single "n$" and
+    // trailing ones are dropped.
+    pending_rows : usize,
+    pending_dead : usize,
+    /// Tag of dead cells, "." in multi-state RLE
+    dead : &'static str,
+}
+
+impl<'a, W : Write> RunWriter<'a, W> {
+    fn push(&mut self, count : usize, tag : &str) -> io::Result<()> {
+        if count == 0 {
+            return Ok(());
+        }
+        let token = match count {
+            1 => tag.to_string(),
+            n => format!("{}{}", n, tag),
+        };
+        if self.line_length + token.len() > MAX_LINE_LENGTH {
+            writeln!(self.out)?;
+            self.line_length = 0;
+        }
+        write!(self.out, "{}", token)?;
+        self.line_length += token.len();
+        Ok(())
+    }
+
+    /// Writes a run of cells that aren't dead after the pending line ends
+    /// and dead cells.
+    fn cells(&mut self, count : usize, tag : &str) -> io::Result<()> {
+        if count == 0 {
+            return Ok(());
+        }
+        self.push(self.pending_rows, "$")?;
+        self.push(self.pending_dead, self.dead)?;
+        self.pending_rows = 0;
+        self.pending_dead = 0;
+        self.push(count, tag)
+    }
+
+    fn dead_cells(&mut self, count : usize) {
+        self.pending_dead += count;
+    }
+
+    fn end_row(&mut self) {
+        self.pending_dead = 0;
+        self.pending_rows += 1;
+    }
+}
+
+/// Tag of a state in multi-state RLE: 'A' to 'X' for the states 1 to 24,
+/// then the same letters after a prefix 'p' to 'y' for the next 24 each.
+fn state_tag(state : u8) -> String {
+    let letter = |i : u8| (b'A' + i) as char;
+    match state {
+        1..=24 => letter(state - 1).to_string(),
+        _ => format!("{}{}", (b'p' + (state - 25) / 24) as char, letter((state - 25) % 24)),
+    }
+}
+
+/// Writes the cells of `region` as an RLE pattern. Cells outside of a
+/// bounded board are written as dead cells. Rules with more than two
+/// states are written in multi-state RLE.
+pub fn write_rle<E, W>(engine : &E,
+                       region : Rect,
+                       comments : &RleComments,
+                       out : &mut W) -> io::Result<()>
+    where E: Engine + ?Sized, W: Write {
+    if !comments.name.is_empty() {
+        writeln!(out, "#N {}", comments.name)?;
+    }
+    if !comments.author.is_empty() {
+        writeln!(out, "#O {}", comments.author)?;
+    }
+    for c in &comments.comments {
+        writeln!(out, "#C {}", c)?;
+    }
+    writeln!(out, "x = {}, y = {}, rule = {}", region.width, region.height, engine.rule())?;
+
+    let width = region.width.max(0) as usize;
+    let multi_state = engine.rule().states() > 2;
+    let mut writer = RunWriter {
+        out,
+        line_length : 0,
+        pending_rows : 0,
+        pending_dead : 0,
+        dead : if multi_state { "." } else { "b" },
+    };
+
+    if multi_state {
+        let mut states = vec![0_u8; width];
+        for row in region.row..(region.row + region.height) {
+            engine.fill_states(Rect { row, height : 1, ..region }, &mut states);
+            for run in states.chunk_by(|a, b| a == b) {
+                match run[0] {
+                    0 => writer.dead_cells(run.len()),
+                    state => writer.cells(run.len(), &state_tag(state))?,
+                }
+            }
+            writer.end_row();
+        }
+        writer.push(1, "!")?;
+        return writeln!(writer.out);
+    }
+
+    // Rows are fetched bit packed, which lets sparse engines skip their
+    // empty parts
+    let mut words = vec![0_u16; width.div_ceil(16)];
+
+    for row in region.row..(region.row + region.height) {
+        engine.fill_words(Rect { row, height : 1, ..region }, &mut words);
+        let mut alive = 0;
+
+        for (col_c, &word) in words.iter().enumerate() {
+            let cells = (width - col_c * 16).min(16);
+            if word == 0 {
+                writer.cells(alive, "o")?;
+                alive = 0;
+                writer.dead_cells(cells);
+                continue;
+            }
+            for i in 0..cells {
+                if (word >> (15 - i)) & 1 == 1 {
+                    alive += 1;
+                } else {
+                    writer.cells(alive, "o")?;
+                    alive = 0;
+                    writer.dead_cells(1);
+                }
+            }
+        }
+        writer.cells(alive, "o")?;
+        writer.end_row();
+    }
+
+    writer.push(1, "!")?;
+    writeln!(writer.out)
+}
+
+/// Writes the cells of `region` as an RLE pattern to the file at `filepath`.
+pub fn save_rle<E, P>(engine : &E,
+                      region : Rect,
+                      comments : &RleComments,
+                      filepath : P) -> io::Result<()>
+    where E: Engine + ?Sized, P: AsRef<Path> {
+    let mut writer = io::BufWriter::new(File::create(filepath)?);
+    write_rle(engine, region, comments, &mut writer)?;
+    writer.flush()
+}
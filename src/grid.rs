@@ -0,0 +1,797 @@
+//! Bit packed board and the parallel kernel stepping it.
+
+extern crate rayon;
+
+use rayon::prelude::*;
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+use crate::engine::{self, Engine, Rect};
+use crate::kernel::{self, Transitions};
+use crate::ltl::Counts;
+use crate::rule::Rule;
+use crate::simd::{self, Backend};
+use crate::table::RuleTable;
+
+/// Rectangular area of the board given by its top left cell and size.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Region {
+    pub col : usize,
+    pub row : usize,
+    pub width : usize,
+    pub height : usize,
+}
+
+/// How the edges of the board are glued together.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Topology {
+    /// Opposite edges are glued, the board wraps around in both directions
+    Torus,
+    /// Cells outside of the board are always dead
+    Plane,
+    /// Left and right edges are glued like on a torus, top and bottom
+    /// edges are glued with a flip: leaving the board at the top of column
+    /// `c` enters it at the bottom of column `num_cols - 1 - c`
+    KleinBottle,
+    /// Both pairs of edges are glued with a flip (real projective plane).
+    /// The corner cells are mapped by first flipping across the top/bottom
+    /// edges, then across the left/right edges.
+    CrossSurface,
+}
+
+impl Topology {
+    /// Maps a position that may lie outside of a `width` x `height` board
+    /// back onto the board, `None` if it refers to a dead border cell.
+    pub fn map(self, col : isize, row : isize, width : usize, height : usize) -> Option<(usize, usize)> {
+        let (width, height) = (width as isize, height as isize);
+        let (mut col, mut row) = (col, row);
+        // Positions farther away than the size of the board wrap around
+        // several times, every other time flipped
+        if row < 0 || row >= height {
+            let flipped = row.div_euclid(height) % 2 != 0;
+            match self {
+                Topology::Plane => return None,
+                Topology::Torus => (),
+                Topology::KleinBottle | Topology::CrossSurface if flipped => col = width - 1 - col,
+                Topology::KleinBottle | Topology::CrossSurface => (),
+            }
+            row = row.rem_euclid(height);
+        }
+        if col < 0 || col >= width {
+            let flipped = col.div_euclid(width) % 2 != 0;
+            match self {
+                Topology::Plane => return None,
+                Topology::CrossSurface if flipped => row = height - 1 - row,
+                Topology::Torus | Topology::KleinBottle | Topology::CrossSurface => (),
+            }
+            col = col.rem_euclid(width);
+        }
+        Some((col as usize, row as usize))
+    }
+}
+
+impl FromStr for Topology {
+    type Err = String;
+
+    fn from_str(s : &str) -> Result<Topology, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "torus" => Ok(Topology::Torus),
+            "plane" => Ok(Topology::Plane),
+            "klein" | "klein-bottle" => Ok(Topology::KleinBottle),
+            "cross" | "cross-surface" => Ok(Topology::CrossSurface),
+            _ => Err(format!("expected \"torus\", \"plane\", \"klein\" or \"cross\", found \"{}\"", s)),
+        }
+    }
+}
+
+impl fmt::Display for Topology {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Topology::Torus => "torus",
+            Topology::Plane => "plane",
+            Topology::KleinBottle => "klein-bottle",
+            Topology::CrossSurface => "cross-surface",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Number of rows of a tile. Tiles are one word wide, i.e. 64x64 cells.
+const TILE_ROWS : usize = 64;
+
+/// Next words, changed tiles and either the ages of the dying cells or
+/// the states of the cells of one row of tiles.
+type Band<'a, T> = ((&'a mut [u64], &'a mut [bool]), &'a mut [T]);
+
+/// Number of bit planes holding the ages of the dying cells of `rule`.
+/// Rule tables keep the states of the cells in bytes instead.
+fn decay_planes(rule : &Rule) -> usize {
+    if rule.rule_table().is_some() { 0 } else { kernel::decay_planes(rule.states()) }
+}
+
+/// Board of `num_cols` x `num_rows` cells whose edges are glued according
+/// to its `Topology`. Each `u64` in `cells` holds 64 horizontally adjacent
+/// cells, the most significant bit being the leftmost cell. Every row
+/// starts with a new word; if `num_cols` is not a multiple of 64 the
+/// unused low bits of the last word of each row are always zero.
+///
+/// With a Generations rule the ages of the dying cells are kept in bit
+/// planes next to `cells`, which only holds the alive ones. With a rule
+/// table the state of every cell is kept in a byte and `cells` holds the
+/// cells that aren't dead.
+///
+/// The board is split into tiles of 64x64 cells. Only tiles that changed
+/// in the previous generation and their neighbours are recomputed, so
+/// empty and settled areas cost next to nothing. Runs of neighbouring
+/// tiles are stepped with the widest SIMD `Backend` the CPU supports.
+#[derive(Clone)]
+pub struct Grid {
+    cells : Vec<u64>,
+    /// The previous generation, overwritten with the next one and then
+    /// swapped with `cells`
+    back : Vec<u64>,
+    pub num_cols : usize,
+    pub num_rows : usize,
+    pub rule : Rule,
+    topology : Topology,
+    /// Ages of the dying cells of a Generations rule, the planes of a
+    /// word of `cells` stored one after the other, see `kernel::next_decay`
+    decay : Vec<u64>,
+    /// The previous ages, swapped with `decay` like `back`
+    decay_back : Vec<u64>,
+    /// States of the cells of a rule table row by row, empty for other rules
+    states : Vec<u8>,
+    /// The previous states, swapped with `states` like `back`
+    states_back : Vec<u8>,
+    /// Tiles that changed in the previous generation or were edited
+    changed : Vec<bool>,
+    backend : Backend,
+    /// Whether bands of tiles are stepped on the threads of the rayon pool
+    parallel : bool,
+    /// Neighbourhood counts of a Larger than Life rule, kept to reuse their
+    /// buffers like `back`
+    counts : Option<Counts>,
+}
+
+impl Grid {
+    pub fn new (board_size : (u32, u32), rule : Rule, topology : Topology) -> Self {
+        let words_per_row = (board_size.0 as usize).div_ceil(64);
+        let cells = vec![0_u64 ; words_per_row * board_size.1 as usize];
+        let num_tiles = words_per_row * (board_size.1 as usize).div_ceil(TILE_ROWS);
+        let decay = vec![0_u64; cells.len() * decay_planes(&rule)];
+        let states = match rule.rule_table() {
+            Some(_) => vec![0_u8; board_size.0 as usize * board_size.1 as usize],
+            None => Vec::new(),
+        };
+        Self {
+            back : cells.clone(),
+            cells,
+            decay_back : decay.clone(),
+            decay,
+            states_back : states.clone(),
+            states,
+            num_cols : board_size.0 as usize,
+            num_rows : board_size.1 as usize,
+            rule,
+            topology,
+            changed : vec![true; num_tiles],
+            backend : Backend::detect(),
+            parallel : true,
+            counts : None,
+        }
+    }
+
+    /// Bit packed cells, see `Grid`.
+    pub fn cells(&self) -> &[u64] {
+        &self.cells
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+
+    /// Switches the kernel implementation, fails if the CPU doesn't
+    /// support the backend.
+    pub fn set_backend(&mut self, backend : Backend) -> Result<(), String> {
+        if !backend.is_supported() {
+            return Err(format!("The {} backend isn't supported by this CPU", backend));
+        }
+        self.backend = backend;
+        Ok(())
+    }
+
+    /// Number of `u64` words a row of `cells` occupies.
+    #[inline]
+    pub fn words_per_row(&self) -> usize {
+        self.num_cols.div_ceil(64)
+    }
+
+    /// Mask of the bits of the last word of a row that hold cells.
+    #[inline]
+    fn tail_mask(&self) -> u64 {
+        match self.num_cols % 64 {
+            0 => u64::MAX,
+            n => u64::MAX << (64 - n),
+        }
+    }
+
+    #[inline]
+    fn coord_to_idx(&self, col : usize, row : usize) -> usize {
+        self.words_per_row() * row  + (col / 64)
+    }
+
+    /// Tile containing word `idx` of `cells`.
+    #[inline]
+    fn tile_of(&self, idx : usize) -> usize {
+        let words_per_row = self.words_per_row();
+        (idx / words_per_row / TILE_ROWS) * words_per_row + idx % words_per_row
+    }
+
+    pub fn set_cell(&mut self, col : usize, row : usize, value : bool) {
+        self.set_state(col, row, value as u8);
+    }
+
+    /// Sets the cell to a state of the rule, ignored for states it doesn't
+    /// have.
+    pub fn set_state(&mut self, col : usize, row : usize, state : u8) {
+        if col < self.num_cols && row < self.num_rows && (state as u16) < self.rule.states() {
+            let idx = self.coord_to_idx(col, row);
+            let mask = 1_u64 << (63 - col % 64);
+            let tile = self.tile_of(idx);
+            self.changed[tile] = true;
+            if !self.states.is_empty() {
+                self.states[row * self.num_cols + col] = state;
+            }
+            if state == 1 || (state != 0 && !self.states.is_empty()) {
+                self.cells[idx] |= mask;
+            } else {
+                self.cells[idx] &= !mask;
+            }
+            let planes = self.decay_planes();
+            let age = (state as usize).saturating_sub(1);
+            for (i, plane) in self.decay[idx * planes..][..planes].iter_mut().enumerate() {
+                if (age >> i) & 1 == 1 {
+                    *plane |= mask;
+                } else {
+                    *plane &= !mask;
+                }
+            }
+        }
+    }
+
+    /// State of the cell: 0 if dead, 1 if alive and 2 or more if dying
+    /// under a Generations rule or in any other state of a rule table.
+    pub fn get_state(&self, col : usize, row : usize) -> u8 {
+        if col >= self.num_cols || row >= self.num_rows {
+            return 0;
+        }
+        if !self.states.is_empty() {
+            return self.states[row * self.num_cols + col];
+        }
+        if self.get_cell(col, row) {
+            return 1;
+        }
+        let idx = self.coord_to_idx(col, row);
+        let planes = self.decay_planes();
+        let age = self.decay[idx * planes..][..planes]
+                      .iter()
+                      .enumerate()
+                      .fold(0, |age, (i, plane)| age | (((plane >> (63 - col % 64)) & 1) << i));
+        if age == 0 { 0 } else { age as u8 + 1 }
+    }
+
+    #[inline]
+    fn decay_planes(&self) -> usize {
+        self.decay.len() / self.cells.len().max(1)
+    }
+
+    pub fn get_cell(&self, col : usize, row : usize) -> bool {
+        if col < self.num_cols && row < self.num_rows {
+            let idx = self.coord_to_idx(col, row);
+            (self.cells[idx] >> (63 - col % 64)) & 0x1 == 0x1
+        } else {
+            false
+        }
+    }
+
+    /// Number of alive cells.
+    pub fn population(&self) -> u64 {
+        self.cells.iter().map(|c| c.count_ones() as u64).sum()
+    }
+
+    /// Smallest region containing all alive cells, `None` if the board is empty.
+    pub fn bounding_box(&self) -> Option<Region> {
+        let num_cols_c = self.words_per_row();
+        let mut min = (usize::MAX, usize::MAX);
+        let mut max = (0, 0);
+
+        for (row, words) in self.cells.chunks(num_cols_c).enumerate() {
+            for (col_c, word) in words.iter().enumerate().filter(|(_, w)| **w != 0) {
+                min.0 = min.0.min(col_c * 64 + word.leading_zeros() as usize);
+                max.0 = max.0.max(col_c * 64 + 63 - word.trailing_zeros() as usize);
+                min.1 = min.1.min(row);
+                max.1 = row;
+            }
+        }
+
+        if min.1 == usize::MAX {
+            return None;
+        }
+        Some(Region {
+            col : min.0,
+            row : min.1,
+            width : max.0 - min.0 + 1,
+            height : max.1 - min.1 + 1,
+        })
+    }
+
+    /// Switches the rule. Cells that are neither dead nor alive are cleared
+    /// if the new rule has a different number of states or only one of the
+    /// rules is a rule table.
+    pub fn set_rule(&mut self, rule : Rule) {
+        if rule.states() != self.rule.states() || rule.rule_table().is_some() != self.rule.rule_table().is_some() {
+            let words_per_row = self.words_per_row();
+            for (idx, _) in self.states.iter().enumerate().filter(|(_, s)| **s > 1) {
+                let (col, row) = (idx % self.num_cols, idx / self.num_cols);
+                self.cells[row * words_per_row + col / 64] &= !(1 << (63 - col % 64));
+            }
+            self.decay = vec![0; self.cells.len() * decay_planes(&rule)];
+            self.decay_back = self.decay.clone();
+            self.states = match rule.rule_table() {
+                Some(_) => (0..self.num_rows).flat_map(|row| (0..self.num_cols).map(move |col| (col, row)))
+                                             .map(|(col, row)| self.get_cell(col, row) as u8)
+                                             .collect(),
+                None => Vec::new(),
+            };
+            self.states_back = self.states.clone();
+        }
+        self.rule = rule;
+        self.changed.iter_mut().for_each(|c| *c = true);
+    }
+
+    /// Number of tiles that will be recomputed in the next generation.
+    pub fn active_tiles(&self) -> usize {
+        self.tiles_to_update().iter().filter(|a| **a).count()
+    }
+
+    /// Tiles whose cells may change in the next generation: the changed
+    /// tiles and their neighbours. With rules that give birth on 0
+    /// neighbours empty areas flicker and Larger than Life neighbourhoods
+    /// reach past the neighbouring tiles, so every tile is updated.
+    fn tiles_to_update(&self) -> Vec<bool> {
+        let births_on_zero = match self.rule.rule_table() {
+            Some(table) => table.next_state(0, &[0; 8][..table.offsets().len()]) != 0,
+            None => self.rule.transitions() & 0b1 != 0,
+        };
+        if births_on_zero || self.rule.larger_than_life().is_some() {
+            return vec![true; self.changed.len()];
+        }
+
+        let tiles_per_row = self.words_per_row() as isize;
+        let tile_rows = self.changed.len() as isize / tiles_per_row.max(1);
+        let mut active = vec![false; self.changed.len()];
+        let mut crosses_edge = false;
+        for tile in (0..self.changed.len()).filter(|t| self.changed[*t]) {
+            let (tx, ty) = ((tile as isize) % tiles_per_row, (tile as isize) / tiles_per_row);
+            for (dx, dy) in (-1..=1).flat_map(|dy| (-1..=1).map(move |dx| (dx, dy))) {
+                let (x, y) = (tx + dx, ty + dy);
+                if x >= 0 && x < tiles_per_row && y >= 0 && y < tile_rows {
+                    active[(y * tiles_per_row + x) as usize] = true;
+                } else {
+                    crosses_edge = true;
+                }
+            }
+        }
+
+        // Cells across a glued edge may lie in any tile along the border,
+        // depending on the topology
+        if crosses_edge && self.topology != Topology::Plane {
+            for (tile, a) in active.iter_mut().enumerate() {
+                let (tx, ty) = ((tile as isize) % tiles_per_row, (tile as isize) / tiles_per_row);
+                if tx == 0 || ty == 0 || tx == tiles_per_row - 1 || ty == tile_rows - 1 {
+                    *a = true;
+                }
+            }
+        }
+
+        active
+    }
+
+    /// Cell at a position that may lie outside of the board, mapped back
+    /// onto the board according to the topology.
+    fn cell_at(&self, col : isize, row : isize) -> bool {
+        match self.topology.map(col, row, self.num_cols, self.num_rows) {
+            Some((col, row)) => self.get_cell(col, row),
+            None => false,
+        }
+    }
+
+    /// 64 cells of `row` starting at `first_col`, the leftmost one in the
+    /// most significant bit. Cells left or right of the row are zero.
+    fn row_bits(&self, row : usize, first_col : isize) -> u64 {
+        let num_cols_c = self.words_per_row() as isize;
+        let word = |col_c : isize| -> u128 {
+            if col_c >= 0 && col_c < num_cols_c {
+                self.cells[row * num_cols_c as usize + col_c as usize] as u128
+            } else {
+                0
+            }
+        };
+        let col_c = first_col.div_euclid(64);
+        let pair = (word(col_c) << 64) | word(col_c + 1);
+        ((pair << first_col.rem_euclid(64)) >> 64) as u64
+    }
+
+    /// Word `col_c` of `row`, which may lie outside of the board, with the
+    /// words left and right of it as taken by `kernel::neighbours`. Only
+    /// the cells next to the word are set in those; for a partial last word
+    /// the cell right of it is put into the first unused bit instead.
+    fn edge_words(&self, col_c : usize, row : isize) -> [u64; 3] {
+        let num_cols_c = self.words_per_row();
+        let num_rows = self.num_rows as isize;
+        let first_col = (col_c * 64) as isize;
+        let width = (self.num_cols - col_c * 64).min(64);
+
+        let word = if row >= 0 && row < num_rows {
+            self.cells[row as usize * num_cols_c + col_c]
+        } else {
+            let wrapped = row.rem_euclid(num_rows) as usize;
+            match self.topology {
+                Topology::Plane => return [0; 3],
+                Topology::Torus => self.cells[wrapped * num_cols_c + col_c],
+                // The row is entered flipped, so the mirrored cells are read backwards
+                Topology::KleinBottle | Topology::CrossSurface =>
+                    self.row_bits(wrapped, self.num_cols as isize - first_col - 64).reverse_bits() &
+                    (u64::MAX << (64 - width)),
+            }
+        };
+
+        let left = self.cell_at(first_col - 1, row) as u64;
+        let right = self.cell_at(first_col + width as isize, row) as u64;
+        if width == 64 {
+            [left, word, right << 63]
+        } else {
+            [left, word | (right << (63 - width)), 0]
+        }
+    }
+
+    /// Next state of word `idx` of `cells`.
+    #[inline]
+    fn next_word(&self, idx : usize, transitions : &Transitions) -> u64 {
+        let num_cols_c = self.words_per_row();
+        let tail_mask = self.tail_mask();
+        let row = idx / num_cols_c;
+        let col_c = idx % num_cols_c;
+        // A partial last word breaks the plain word wrap-around of the torus
+        let wrap_cols = self.topology == Topology::Torus && tail_mask == u64::MAX;
+        let wrap_rows = self.topology == Topology::Torus;
+        let is_edge_row = row == 0 || row == self.num_rows - 1;
+        let is_edge_col = col_c == 0 || col_c == num_cols_c - 1;
+
+        let (above, center, below) = if (is_edge_row && !wrap_rows) || (is_edge_col && !wrap_cols) {
+            let row = row as isize;
+            (self.edge_words(col_c, row - 1), self.edge_words(col_c, row), self.edge_words(col_c, row + 1))
+        } else {
+            let size = self.cells.len();
+            let left = (col_c + num_cols_c - 1) % num_cols_c;
+            let right = (col_c + 1) % num_cols_c;
+            let words = |row_off : usize| [self.cells[row_off + left],
+                                           self.cells[row_off + col_c],
+                                           self.cells[row_off + right]];
+            let row_off = row * num_cols_c;
+            (words((row_off + size - num_cols_c) % size), words(row_off), words((row_off + num_cols_c) % size))
+        };
+
+        let next = kernel::next_word(above, center, below, transitions);
+        if col_c == num_cols_c - 1 { next & tail_mask } else { next }
+    }
+
+    /// Writes the next state of the words of `row` that lie in `active`
+    /// tiles to `out` and flags the tiles whose words changed.
+    fn next_row(&self, row : usize, active : &[bool], out : &mut [u64], changed : &mut [bool], transitions : &Transitions) {
+        let num_cols_c = self.words_per_row();
+        let words = |row : usize| &self.cells[row * num_cols_c..][..num_cols_c];
+        let current = words(row);
+        // Words whose neighbours all lie in the row and the rows above and
+        // below it are stepped in runs by the SIMD kernel, lookup tables
+        // are only evaluated word by word
+        let interior_row = self.topology == Topology::Torus || (row > 0 && row < self.num_rows - 1);
+        let simd_transitions = match transitions {
+            Transitions::Totalistic(transitions) if interior_row => Some(*transitions),
+            _ => None,
+        };
+        let (above, below) = ((row + self.num_rows - 1) % self.num_rows, (row + 1) % self.num_rows);
+
+        let mut col_c = 0;
+        while col_c < num_cols_c {
+            if !active[col_c] {
+                col_c += 1;
+                continue;
+            }
+            let end = if simd_transitions.is_some() && col_c > 0 {
+                (col_c..num_cols_c - 1).find(|c| !active[*c]).unwrap_or(num_cols_c - 1)
+            } else {
+                col_c
+            };
+            if let (true, Some(transitions)) = (end > col_c, simd_transitions) {
+                let span = col_c - 1..end + 1;
+                simd::next_words(self.backend, &words(above)[span.clone()], &current[span.clone()],
+                                 &words(below)[span], &mut out[col_c..end], transitions);
+            } else {
+                out[col_c] = self.next_word(row * num_cols_c + col_c, transitions);
+            }
+            for c in col_c..end.max(col_c + 1) {
+                changed[c] |= out[c] != current[c];
+            }
+            col_c = end.max(col_c + 1);
+        }
+    }
+
+    /// Ages the dying cells of the active tiles of a band whose first word
+    /// is `first_idx` and whose next alive cells are in `words`, writing
+    /// the next ages to `decay`. Tiles with dying cells always change.
+    fn decay_band(&self, first_idx : usize, active : &[bool], words : &mut [u64], decay : &mut [u64], changed : &mut [bool]) {
+        let num_cols_c = self.words_per_row();
+        let planes = self.decay_planes();
+        for (offset, word) in words.iter_mut().enumerate() {
+            let col_c = offset % num_cols_c;
+            if !active[col_c] {
+                continue;
+            }
+            let idx = first_idx + offset;
+            let current = &self.decay[idx * planes..][..planes];
+            let next = &mut decay[offset * planes..][..planes];
+            *word = kernel::next_decay(self.cells[idx], *word, current, next, self.rule.states());
+            changed[col_c] |= current.iter().any(|plane| *plane != 0);
+        }
+    }
+
+    /// State of a cell of a rule table at a position that may lie outside
+    /// of the board, mapped back onto the board according to the topology.
+    fn state_at(&self, col : isize, row : isize) -> u8 {
+        match self.topology.map(col, row, self.num_cols, self.num_rows) {
+            Some((col, row)) => self.states[row * self.num_cols + col],
+            None => 0,
+        }
+    }
+
+    /// Writes the next states of the cells of `row` that lie in `active`
+    /// tiles to `states` and the cells that aren't dead to `out`, and flags
+    /// the tiles whose cells changed.
+    fn next_table_row(&self, table : &RuleTable, row : usize, active : &[bool],
+                      out : &mut [u64], states : &mut [u8], changed : &mut [bool]) {
+        let width = self.num_cols;
+        let current = &self.states[row * width..][..width];
+        let offsets = table.offsets();
+        let mut neighbours = [0_u8; 8];
+        let neighbours = &mut neighbours[..offsets.len()];
+        let interior_row = row > 0 && row < self.num_rows - 1;
+        for (col_c, word) in out.iter_mut().enumerate().filter(|(col_c, _)| active[*col_c]) {
+            *word = 0;
+            for col in col_c * 64..(col_c * 64 + 64).min(width) {
+                let (c, r) = (col as isize, row as isize);
+                if interior_row && col > 0 && col < width - 1 {
+                    for (n, (dx, dy)) in neighbours.iter_mut().zip(offsets) {
+                        *n = self.states[((r + dy) * width as isize + c + dx) as usize];
+                    }
+                } else {
+                    for (n, (dx, dy)) in neighbours.iter_mut().zip(offsets) {
+                        *n = self.state_at(c + dx, r + dy);
+                    }
+                }
+                let next = table.next_state(current[col], neighbours);
+                states[col] = next;
+                *word |= ((next != 0) as u64) << (63 - col % 64);
+                changed[col_c] |= next != current[col];
+            }
+        }
+    }
+
+    /// Advances the board of a rule table by one generation.
+    fn run_table_lifecycle(&mut self, active : Vec<bool>) {
+        let num_cols_c = self.words_per_row();
+        let mut back = std::mem::take(&mut self.back);
+        let mut states_back = std::mem::take(&mut self.states_back);
+        let mut changed = std::mem::take(&mut self.changed);
+        let this = &*self;
+        let table = this.rule.rule_table().unwrap();
+
+        let step_band = |(band, ((words, changed), states)) : (usize, Band<u8>)| {
+            let active = &active[band * num_cols_c..][..num_cols_c];
+            changed.iter_mut().for_each(|c| *c = false);
+            if !active.contains(&true) {
+                return;
+            }
+            for (row, (out, states)) in words.chunks_mut(num_cols_c).zip(states.chunks_mut(this.num_cols)).enumerate() {
+                this.next_table_row(table, band * TILE_ROWS + row, active, out, states, changed);
+            }
+        };
+        if self.parallel {
+            back.par_chunks_mut(num_cols_c * TILE_ROWS)
+                .zip(changed.par_chunks_mut(num_cols_c))
+                .zip(states_back.par_chunks_mut(self.num_cols * TILE_ROWS))
+                .enumerate()
+                .for_each(step_band);
+        } else {
+            back.chunks_mut(num_cols_c * TILE_ROWS)
+                .zip(changed.chunks_mut(num_cols_c))
+                .zip(states_back.chunks_mut(self.num_cols * TILE_ROWS))
+                .enumerate()
+                .for_each(step_band);
+        }
+
+        self.states_back = std::mem::replace(&mut self.states, states_back);
+        self.changed = changed;
+        self.back = std::mem::replace(&mut self.cells, back);
+    }
+
+    /// Advances the board by one generation.
+    pub fn run_lifecycle(&mut self) {
+        let transitions = Transitions::of(&self.rule);
+        let num_cols_c = self.words_per_row();
+        if self.cells.is_empty() {
+            return;
+        }
+        if self.rule.rule_table().is_some() {
+            let active = self.tiles_to_update();
+            return self.run_table_lifecycle(active);
+        }
+
+        let active = self.tiles_to_update();
+        let mut counts = match (self.rule.larger_than_life(), self.counts.take()) {
+            (Some(rule), Some(counts)) if counts.fits(rule, self.num_cols, self.num_rows) => Some(counts),
+            (Some(rule), _) => Some(Counts::new(rule, self.num_cols, self.num_rows)),
+            (None, _) => None,
+        };
+        if let Some(counts) = &mut counts {
+            counts.fill(self.parallel, |col, row| self.cell_at(col, row));
+        }
+        let mut back = std::mem::take(&mut self.back);
+        let mut decay_back = std::mem::take(&mut self.decay_back);
+        let mut changed = std::mem::take(&mut self.changed);
+        let this = &*self;
+
+        // The ages of the dying cells of each band, empty for Life-like rules
+        let band_words = num_cols_c * TILE_ROWS;
+        let planes = self.decay_planes();
+        let mut decay_bands = decay_back.chunks_mut(band_words * planes.max(1)).collect::<Vec<_>>();
+        decay_bands.resize_with(back.len().div_ceil(band_words), Default::default);
+
+        // Every task computes a band of one tile row; words of inactive
+        // tiles are left alone as they already hold the current generation
+        let step_band = |(band, ((words, changed), decay)) : (usize, Band<u64>)| {
+            let active = &active[band * num_cols_c..][..num_cols_c];
+            changed.iter_mut().for_each(|c| *c = false);
+            if !active.contains(&true) {
+                return;
+            }
+            for (row, out) in words.chunks_mut(num_cols_c).enumerate() {
+                let row = band * TILE_ROWS + row;
+                match &counts {
+                    Some(counts) => {
+                        let current = &this.cells[row * num_cols_c..][..num_cols_c];
+                        counts.next_row(row, current, out);
+                        for (c, (next, current)) in changed.iter_mut().zip(out.iter().zip(current)) {
+                            *c |= next != current;
+                        }
+                    }
+                    None => this.next_row(row, active, out, changed, &transitions),
+                }
+            }
+            if planes > 0 {
+                this.decay_band(band * band_words, active, words, decay, changed);
+            }
+        };
+        if self.parallel {
+            back.par_chunks_mut(band_words)
+                .zip(changed.par_chunks_mut(num_cols_c))
+                .zip(decay_bands)
+                .enumerate()
+                .for_each(step_band);
+        } else {
+            back.chunks_mut(band_words)
+                .zip(changed.chunks_mut(num_cols_c))
+                .zip(decay_bands)
+                .enumerate()
+                .for_each(step_band);
+        }
+
+        self.decay_back = std::mem::replace(&mut self.decay, decay_back);
+        self.changed = changed;
+        self.back = std::mem::replace(&mut self.cells, back);
+        self.counts = counts;
+    }
+}
+
+impl Engine for Grid {
+    fn rule(&self) -> Rule {
+        self.rule.clone()
+    }
+
+    fn set_rule(&mut self, rule : Rule) -> Result<(), String> {
+        Grid::set_rule(self, rule);
+        Ok(())
+    }
+
+    fn get_cell(&self, col : i64, row : i64) -> bool {
+        col >= 0 && row >= 0 && Grid::get_cell(self, col as usize, row as usize)
+    }
+
+    fn set_cell(&mut self, col : i64, row : i64, value : bool) {
+        if col >= 0 && row >= 0 {
+            Grid::set_cell(self, col as usize, row as usize, value);
+        }
+    }
+
+    fn get_state(&self, col : i64, row : i64) -> u8 {
+        if col >= 0 && row >= 0 { Grid::get_state(self, col as usize, row as usize) } else { 0 }
+    }
+
+    fn set_state(&mut self, col : i64, row : i64, state : u8) {
+        if col >= 0 && row >= 0 {
+            Grid::set_state(self, col as usize, row as usize, state);
+        }
+    }
+
+    fn population(&self) -> u64 {
+        Grid::population(self)
+    }
+
+    fn bounding_box(&self) -> Option<Rect> {
+        Grid::bounding_box(self).map(Rect::from)
+    }
+
+    fn step(&mut self, exponent : u32) -> Result<(), String> {
+        engine::check_step_exponent(exponent, Engine::max_step_exponent(self))?;
+        for _ in 0..(1_u64 << exponent) {
+            self.run_lifecycle();
+        }
+        Ok(())
+    }
+
+    fn max_step_exponent(&self) -> u32 {
+        engine::MAX_ITERATED_STEP_EXPONENT
+    }
+
+    fn state_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.cells.hash(&mut hasher);
+        self.decay.hash(&mut hasher);
+        self.states.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn fill_words(&self, area : Rect, words : &mut [u16]) {
+        let words_per_row = (area.width as usize).div_ceil(16);
+        let tail_mask = match area.width % 16 {
+            0 => u16::MAX,
+            n => u16::MAX << (16 - n),
+        };
+        for (r, out) in words.chunks_mut(words_per_row).enumerate() {
+            let row = area.row + r as i64;
+            for (i, chunk) in out.chunks_mut(4).enumerate() {
+                let bits = if row >= 0 && row < self.num_rows as i64 {
+                    self.row_bits(row as usize, (area.col + i as i64 * 64) as isize)
+                } else {
+                    0
+                };
+                for (j, word) in chunk.iter_mut().enumerate() {
+                    *word = (bits >> (48 - 16 * j)) as u16;
+                }
+            }
+            if let Some(last) = out.last_mut() {
+                *last &= tail_mask;
+            }
+        }
+    }
+
+    fn set_parallel(&mut self, parallel : bool) {
+        self.parallel = parallel;
+    }
+}
@@ -0,0 +1,619 @@
+//! HashLife engine: a memoized quadtree of the unbounded plane.
+//!
+//! The universe is a square quadtree whose nodes are canonical, equal
+//! subpatterns share one node. Every node caches its center advanced by
+//! each step size it was asked for, so repeating structures are computed
+//! once and large step sizes cost about as much as small ones.
+
+use std::collections::HashMap;
+
+use crate::engine::{self, Engine, Rect};
+use crate::kernel::{self, Transitions};
+use crate::rule::Rule;
+
+/// Level of the leaves, which hold 8x8 cells each.
+const LEAF_LEVEL : u32 = 3;
+/// Highest level the root may grow to, keeping coordinates inside `i64`.
+pub(crate) const MAX_LEVEL : u32 = 62;
+/// Number of cached nodes and results after which the cache is garbage
+/// collected.
+pub const DEFAULT_NODE_LIMIT : usize = 1 << 22;
+/// Marks a missing result.
+const NONE : u32 = u32::MAX;
+/// Bases of the state hash, an alive cell at (col, row) adds
+/// `HASH_COL^col * HASH_ROW^row`. Both are odd and thus invertible, which
+/// makes the hash well defined for negative coordinates.
+const HASH_COL : u64 = 0x9e37_79b9_7f4a_7c15;
+const HASH_ROW : u64 = 0xc2b2_ae3d_27d4_eb4f;
+
+#[derive(Copy, Clone)]
+struct Node {
+    /// Children in the order nw, ne, sw, se, unused for leaves
+    children : [u32; 4],
+    /// Cells of a leaf row by row, the top left cell in the most
+    /// significant bit
+    bits : u64,
+    level : u32,
+    population : u64,
+    /// State hash of the node's cells relative to its top left corner
+    hash : u64,
+    /// Center of the node advanced by `2^result_exponent` generations, the
+    /// result of the latest step size the node was asked for
+    result : u32,
+    /// Small nodes keep their results when the step size changes, as a
+    /// node of level `l` never advances by more than `2^(l - 2)`
+    result_exponent : u32,
+}
+
+/// Unbounded universe stepped with Bill Gosper's HashLife algorithm.
+pub struct HashLife {
+    nodes : Vec<Node>,
+    leaves : HashMap<u64, u32>,
+    branches : HashMap<[u32; 4], u32>,
+    /// Results of nodes for the step sizes other than the one in their
+    /// slot, keyed by node and exponent. Only filled when the step size
+    /// changes, e.g. by the smaller steps finishing `Simulation::advance`.
+    other_results : HashMap<(u32, u32), u32>,
+    /// Empty node of each level, indexed by `level - LEAF_LEVEL`
+    empty : Vec<u32>,
+    root : u32,
+    /// Universe coordinates of the root's top left cell
+    origin : (i64, i64),
+    rule : Rule,
+    /// The rule as evaluated by the kernel, kept as building a lookup
+    /// table for every leaf would be slow
+    transitions : Transitions,
+    node_limit : usize,
+    /// `HASH_COL^(2^level)` and `HASH_ROW^(2^level)`
+    col_factors : [u64; 64],
+    row_factors : [u64; 64],
+}
+
+impl HashLife {
+    /// Creates an empty universe, fails for rules with birth on 0
+    /// neighbours which would fill the unbounded plane and for Generations,
+    /// Larger than Life and table rules.
+    pub fn new(rule : Rule) -> Result<HashLife, String> {
+        Self::check_rule(&rule)?;
+        let mut col_factors = [HASH_COL; 64];
+        let mut row_factors = [HASH_ROW; 64];
+        for i in 1..64 {
+            col_factors[i] = col_factors[i - 1].wrapping_mul(col_factors[i - 1]);
+            row_factors[i] = row_factors[i - 1].wrapping_mul(row_factors[i - 1]);
+        }
+
+        let mut life = HashLife {
+            nodes : Vec::new(),
+            leaves : HashMap::new(),
+            branches : HashMap::new(),
+            other_results : HashMap::new(),
+            empty : Vec::new(),
+            root : NONE,
+            origin : (-8, -8),
+            transitions : Transitions::of(&rule),
+            rule,
+            node_limit : DEFAULT_NODE_LIMIT,
+            col_factors,
+            row_factors,
+        };
+        life.root = life.empty(LEAF_LEVEL + 1);
+        Ok(life)
+    }
+
+    /// Sets the number of cached nodes and results after which unreachable
+    /// ones are freed. The limit is raised if most of them are still in use
+    /// after a collection.
+    pub fn set_node_limit(&mut self, node_limit : usize) {
+        self.node_limit = node_limit;
+    }
+
+    /// Number of nodes currently in the cache.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Number of results cached besides the one of each node, for step
+    /// sizes other than the latest one.
+    pub fn other_result_count(&self) -> usize {
+        self.other_results.len()
+    }
+
+    fn check_rule(rule : &Rule) -> Result<(), String> {
+        if rule.transitions() & 0b1 != 0 {
+            return Err(format!("HashLife can't simulate rule {} with birth on 0 neighbours", rule));
+        }
+        if rule.rule_table().is_some() {
+            return Err(format!("HashLife can't simulate the rule table {}", rule));
+        }
+        if rule.states() > 2 {
+            return Err(format!("HashLife can't simulate the Generations rule {}", rule));
+        }
+        if rule.larger_than_life().is_some() {
+            return Err(format!("HashLife can't simulate the Larger than Life rule {}", rule));
+        }
+        Ok(())
+    }
+
+    fn leaf(&mut self, bits : u64) -> u32 {
+        if let Some(&id) = self.leaves.get(&bits) {
+            return id;
+        }
+        let mut hash = 0_u64;
+        let mut rest = bits;
+        while rest != 0 {
+            let idx = 63 - rest.trailing_zeros();
+            let (col, row) = (idx % 8, idx / 8);
+            hash = hash.wrapping_add(HASH_COL.wrapping_pow(col).wrapping_mul(HASH_ROW.wrapping_pow(row)));
+            rest &= rest - 1;
+        }
+        let id = self.nodes.len() as u32;
+        self.nodes.push(Node {
+            children : [NONE; 4],
+            bits,
+            level : LEAF_LEVEL,
+            population : bits.count_ones() as u64,
+            hash,
+            result : NONE,
+            result_exponent : 0,
+        });
+        self.leaves.insert(bits, id);
+        id
+    }
+
+    fn join(&mut self, children : [u32; 4]) -> u32 {
+        if let Some(&id) = self.branches.get(&children) {
+            return id;
+        }
+        let [nw, ne, sw, se] = children.map(|c| self.nodes[c as usize]);
+        let col_factor = self.col_factors[nw.level as usize];
+        let row_factor = self.row_factors[nw.level as usize];
+        let hash = nw.hash
+                   .wrapping_add(ne.hash.wrapping_mul(col_factor))
+                   .wrapping_add(sw.hash.wrapping_mul(row_factor))
+                   .wrapping_add(se.hash.wrapping_mul(col_factor).wrapping_mul(row_factor));
+        let id = self.nodes.len() as u32;
+        self.nodes.push(Node {
+            children,
+            bits : 0,
+            level : nw.level + 1,
+            population : nw.population.saturating_add(ne.population)
+                                      .saturating_add(sw.population)
+                                      .saturating_add(se.population),
+            hash,
+            result : NONE,
+            result_exponent : 0,
+        });
+        self.branches.insert(children, id);
+        id
+    }
+
+    fn empty(&mut self, level : u32) -> u32 {
+        let idx = (level - LEAF_LEVEL) as usize;
+        if let Some(&id) = self.empty.get(idx) {
+            return id;
+        }
+        let id = if level == LEAF_LEVEL {
+            self.leaf(0)
+        } else {
+            let child = self.empty(level - 1);
+            self.join([child; 4])
+        };
+        self.empty.push(id);
+        id
+    }
+
+    fn node(&self, id : u32) -> Node {
+        self.nodes[id as usize]
+    }
+
+    fn children(&self, id : u32) -> [Node; 4] {
+        self.node(id).children.map(|c| self.node(c))
+    }
+
+    fn size(&self) -> i64 {
+        1 << self.node(self.root).level
+    }
+
+    fn contains(&self, col : i64, row : i64) -> bool {
+        // Differences of far away coordinates overflow an i64
+        let inside = |x : i64, origin : i64| x >= origin && (x as i128 - origin as i128) < self.size() as i128;
+        inside(col, self.origin.0) && inside(row, self.origin.1)
+    }
+
+    /// Doubles the size of the universe, keeping the pattern centered.
+    /// Fails if it already has the largest size.
+    fn expand(&mut self) -> Result<(), String> {
+        let root = self.node(self.root);
+        if root.level >= MAX_LEVEL {
+            return Err(format!("The pattern grows beyond the HashLife universe of 2^{} x 2^{} cells",
+                               MAX_LEVEL, MAX_LEVEL));
+        }
+        let e = self.empty(root.level - 1);
+        let [nw, ne, sw, se] = root.children;
+        let children = [self.join([e, e, e, nw]),
+                        self.join([e, e, ne, e]),
+                        self.join([e, sw, e, e]),
+                        self.join([se, e, e, e])];
+        self.root = self.join(children);
+        let half = 1 << (root.level - 1);
+        self.origin = (self.origin.0 - half, self.origin.1 - half);
+        Ok(())
+    }
+
+    /// Whether all alive cells lie in the central half of the root.
+    fn is_centered(&self) -> bool {
+        let [nw, ne, sw, se] = self.children(self.root);
+        let border = [(nw, [0, 1, 2]), (ne, [0, 1, 3]), (sw, [0, 2, 3]), (se, [1, 2, 3])];
+        border.iter().all(|(child, idxs)| {
+            idxs.iter().all(|&i| self.node(child.children[i]).population == 0)
+        })
+    }
+
+    fn with_cell(&mut self, id : u32, col : u64, row : u64, value : bool) -> u32 {
+        let node = self.node(id);
+        if node.level == LEAF_LEVEL {
+            let mask = 1_u64 << (63 - (row * 8 + col));
+            let bits = if value { node.bits | mask } else { node.bits & !mask };
+            return self.leaf(bits);
+        }
+        let half = 1_u64 << (node.level - 1);
+        let idx = (row >= half) as usize * 2 + (col >= half) as usize;
+        let mut children = node.children;
+        children[idx] = self.with_cell(children[idx], col % half, row % half, value);
+        self.join(children)
+    }
+
+    /// Cells of a level 4 node as 16 rows of 16 cells.
+    fn rows16(&self, id : u32) -> [u16; 16] {
+        let [nw, ne, sw, se] = self.children(id);
+        let mut rows = [0_u16; 16];
+        for (r, row) in rows.iter_mut().enumerate() {
+            let (left, right) = if r < 8 { (nw.bits, ne.bits) } else { (sw.bits, se.bits) };
+            let shift = 56 - 8 * (r % 8);
+            *row = ((((left >> shift) & 0xff) << 8) | ((right >> shift) & 0xff)) as u16;
+        }
+        rows
+    }
+
+    /// Leaf of the 8x8 cells of `rows` whose top left cell is at (col, row).
+    fn leaf_from_rows(&mut self, rows : &[u16; 16], col : usize, row : usize) -> u32 {
+        let mut bits = 0_u64;
+        for r in 0..8 {
+            bits = (bits << 8) | ((rows[row + r] >> (8 - col)) & 0xff) as u64;
+        }
+        self.leaf(bits)
+    }
+
+    /// Advances 16 rows of 16 cells by one generation, cells outside of
+    /// them count as dead.
+    fn step_rows16(&self, rows : &[u16; 16]) -> [u16; 16] {
+        let row = |r : usize| -> [u64; 3] {
+            // The cells sit in the upper bits so the ones shifted out are dropped
+            [0, rows.get(r).map_or(0, |&w| (w as u64) << 48), 0]
+        };
+        let mut next = [0_u16; 16];
+        for (r, out) in next.iter_mut().enumerate() {
+            *out = (kernel::next_word(row(r.wrapping_sub(1)), row(r), row(r + 1), &self.transitions) >> 48) as u16;
+        }
+        next
+    }
+
+    /// Central node one level below `id`, without advancing it.
+    fn centre(&mut self, id : u32) -> u32 {
+        let node = self.node(id);
+        if node.level == LEAF_LEVEL + 1 {
+            let rows = self.rows16(id);
+            return self.leaf_from_rows(&rows, 4, 4);
+        }
+        let [nw, ne, sw, se] = self.children(id);
+        self.join([nw.children[3], ne.children[2], sw.children[1], se.children[0]])
+    }
+
+    /// Central node one level below `id` advanced by `2^exponent`
+    /// generations, where `exponent <= level - 2`.
+    fn advance(&mut self, id : u32, exponent : u32) -> u32 {
+        let node = self.node(id);
+        if node.result != NONE && node.result_exponent == exponent {
+            return node.result;
+        }
+        if let Some(&result) = self.other_results.get(&(id, exponent)) {
+            return result;
+        }
+        if node.population == 0 {
+            let result = self.empty(node.level - 1);
+            self.set_result(id, result, exponent);
+            return result;
+        }
+
+        let result = if node.level == LEAF_LEVEL + 1 {
+            let mut rows = self.rows16(id);
+            for _ in 0..(1 << exponent) {
+                rows = self.step_rows16(&rows);
+            }
+            self.leaf_from_rows(&rows, 4, 4)
+        } else {
+            // Nine overlapping nodes one level below, built from the
+            // grandchildren g[child][grandchild]
+            let [nw, ne, sw, se] = node.children;
+            let g = self.children(id).map(|c| c.children);
+            let n = [
+                nw,
+                self.join([g[0][1], g[1][0], g[0][3], g[1][2]]),
+                ne,
+                self.join([g[0][2], g[0][3], g[2][0], g[2][1]]),
+                self.join([g[0][3], g[1][2], g[2][1], g[3][0]]),
+                self.join([g[1][2], g[1][3], g[3][0], g[3][1]]),
+                sw,
+                self.join([g[2][1], g[3][0], g[2][3], g[3][2]]),
+                se,
+            ];
+
+            // With the full step of 2^(level - 2) generations both halves
+            // advance, otherwise only the second one does
+            let full = exponent == node.level - 2;
+            let sub_exponent = if full { exponent - 1 } else { exponent };
+            let r = n.map(|c| if full { self.advance(c, sub_exponent) } else { self.centre(c) });
+
+            let quads = [self.join([r[0], r[1], r[3], r[4]]),
+                         self.join([r[1], r[2], r[4], r[5]]),
+                         self.join([r[3], r[4], r[6], r[7]]),
+                         self.join([r[4], r[5], r[7], r[8]])];
+            let children = quads.map(|q| self.advance(q, sub_exponent));
+            self.join(children)
+        };
+        self.set_result(id, result, exponent);
+        result
+    }
+
+    /// Caches the result of node `id` for `2^exponent` generations in its
+    /// slot, moving the result of another step size aside.
+    fn set_result(&mut self, id : u32, result : u32, exponent : u32) {
+        let node = &mut self.nodes[id as usize];
+        if node.result != NONE && node.result_exponent != exponent {
+            self.other_results.insert((id, node.result_exponent), node.result);
+        }
+        node.result = result;
+        node.result_exponent = exponent;
+    }
+
+    /// Drops all nodes not reachable from the root and compacts the cache.
+    pub fn collect_garbage(&mut self) {
+        let mut reachable = vec![false; self.nodes.len()];
+        let mut stack = vec![self.root];
+        while let Some(id) = stack.pop() {
+            if reachable[id as usize] {
+                continue;
+            }
+            reachable[id as usize] = true;
+            let node = self.node(id);
+            if node.level > LEAF_LEVEL {
+                stack.extend_from_slice(&node.children);
+            }
+        }
+
+        // Children are always created before their parents, so a single
+        // pass in creation order remaps them
+        let mut new_ids = vec![NONE; self.nodes.len()];
+        let mut nodes = Vec::new();
+        for (id, node) in self.nodes.iter().enumerate().filter(|(id, _)| reachable[*id]) {
+            new_ids[id] = nodes.len() as u32;
+            nodes.push(*node);
+        }
+        self.leaves.clear();
+        self.branches.clear();
+        for (id, node) in nodes.iter_mut().enumerate() {
+            if node.result != NONE {
+                node.result = new_ids[node.result as usize];
+            }
+            if node.level == LEAF_LEVEL {
+                self.leaves.insert(node.bits, id as u32);
+            } else {
+                node.children = node.children.map(|c| new_ids[c as usize]);
+                self.branches.insert(node.children, id as u32);
+            }
+        }
+        self.nodes = nodes;
+        self.root = new_ids[self.root as usize];
+        self.empty.clear();
+        // Results of dropped nodes or leading to them are dropped as well
+        self.other_results = self.other_results
+                                 .drain()
+                                 .map(|((id, exponent), result)| ((new_ids[id as usize], exponent), new_ids[result as usize]))
+                                 .filter(|((id, _), result)| *id != NONE && *result != NONE)
+                                 .collect();
+
+        if self.nodes.len() + self.other_results.len() > self.node_limit / 2 {
+            self.node_limit *= 2;
+        }
+    }
+
+    fn collect_garbage_if_full(&mut self) {
+        if self.nodes.len() + self.other_results.len() > self.node_limit {
+            self.collect_garbage();
+        }
+    }
+
+    /// Offset of the first (or with `last` the last) alive cell of node
+    /// `id` along `axis`, 0 for columns and 1 for rows.
+    fn extreme(&self, id : u32, axis : usize, last : bool, memo : &mut HashMap<u32, u64>) -> u64 {
+        if let Some(&offset) = memo.get(&id) {
+            return offset;
+        }
+        let node = self.node(id);
+        let offset = if node.level == LEAF_LEVEL {
+            let bits = match axis {
+                0 => (0..8).fold(0_u8, |acc, r| acc | (node.bits >> (56 - 8 * r)) as u8),
+                _ => (0..8).fold(0_u8, |acc, r| acc | (((node.bits >> (56 - 8 * r)) as u8 != 0) as u8) << (7 - r)),
+            };
+            if last { 7 - bits.trailing_zeros() as u64 } else { bits.leading_zeros() as u64 }
+        } else {
+            let half = 1_u64 << (node.level - 1);
+            // Children on the near and the far side of the axis
+            let (near, far) = match axis {
+                0 => ([0, 2], [1, 3]),
+                _ => ([0, 1], [2, 3]),
+            };
+            let (first, second, first_off, second_off) = match last {
+                false => (near, far, 0, half),
+                true => (far, near, half, 0),
+            };
+            let side = |idxs : [usize; 2]| idxs.iter()
+                                              .map(|&i| node.children[i])
+                                              .filter(|&c| self.node(c).population > 0)
+                                              .collect::<Vec<_>>();
+            let (mut children, mut off) = (side(first), first_off);
+            if children.is_empty() {
+                children = side(second);
+                off = second_off;
+            }
+            let offsets = children.iter().map(|&c| self.extreme(c, axis, last, memo));
+            off + if last { offsets.max() } else { offsets.min() }.unwrap_or(0)
+        };
+        memo.insert(id, offset);
+        offset
+    }
+
+    fn fill(&self, id : u32, corner : (i64, i64), area : Rect, words : &mut [u16]) {
+        let node = self.node(id);
+        let size = 1_i64 << node.level;
+        if node.population == 0 ||
+           corner.0 >= area.col + area.width || corner.0 + size <= area.col ||
+           corner.1 >= area.row + area.height || corner.1 + size <= area.row {
+            return;
+        }
+        if node.level == LEAF_LEVEL {
+            let words_per_row = ((area.width + 15) / 16) as usize;
+            let mut rest = node.bits;
+            while rest != 0 {
+                let idx = 63 - rest.trailing_zeros() as i64;
+                rest &= rest - 1;
+                let col = corner.0 + idx % 8 - area.col;
+                let row = corner.1 + idx / 8 - area.row;
+                if col >= 0 && col < area.width && row >= 0 && row < area.height {
+                    words[row as usize * words_per_row + col as usize / 16] |= 0x8000 >> (col % 16);
+                }
+            }
+            return;
+        }
+        let half = size / 2;
+        for (i, &child) in node.children.iter().enumerate() {
+            let corner = (corner.0 + (i as i64 % 2) * half, corner.1 + (i as i64 / 2) * half);
+            self.fill(child, corner, area, words);
+        }
+    }
+}
+
+fn wrapping_pow(base : u64, exponent : u64) -> u64 {
+    let (mut result, mut base, mut exponent) = (1_u64, base, exponent);
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = result.wrapping_mul(base);
+        }
+        base = base.wrapping_mul(base);
+        exponent >>= 1;
+    }
+    result
+}
+
+impl Engine for HashLife {
+    fn rule(&self) -> Rule {
+        self.rule.clone()
+    }
+
+    fn set_rule(&mut self, rule : Rule) -> Result<(), String> {
+        Self::check_rule(&rule)?;
+        if rule != self.rule {
+            self.transitions = Transitions::of(&rule);
+            self.rule = rule;
+            for node in self.nodes.iter_mut() {
+                node.result = NONE;
+            }
+            self.other_results.clear();
+        }
+        Ok(())
+    }
+
+    fn get_cell(&self, col : i64, row : i64) -> bool {
+        if !self.contains(col, row) {
+            return false;
+        }
+        let (mut col, mut row) = ((col - self.origin.0) as u64, (row - self.origin.1) as u64);
+        let mut node = self.node(self.root);
+        while node.level > LEAF_LEVEL {
+            let half = 1_u64 << (node.level - 1);
+            node = self.node(node.children[(row >= half) as usize * 2 + (col >= half) as usize]);
+            col %= half;
+            row %= half;
+        }
+        (node.bits >> (63 - (row * 8 + col))) & 1 == 1
+    }
+
+    fn set_cell(&mut self, col : i64, row : i64, value : bool) {
+        if !value && !self.contains(col, row) {
+            return;
+        }
+        // Cells beyond the largest universe are ignored like the ones
+        // outside of a bounded board
+        while !self.contains(col, row) {
+            if self.expand().is_err() {
+                return;
+            }
+        }
+        let (col, row) = ((col - self.origin.0) as u64, (row - self.origin.1) as u64);
+        self.root = self.with_cell(self.root, col, row, value);
+        self.collect_garbage_if_full();
+    }
+
+    fn population(&self) -> u64 {
+        self.node(self.root).population
+    }
+
+    fn bounding_box(&self) -> Option<Rect> {
+        if self.population() == 0 {
+            return None;
+        }
+        let extreme = |axis, last| self.extreme(self.root, axis, last, &mut HashMap::new()) as i64;
+        let (left, right) = (extreme(0, false), extreme(0, true));
+        let (top, bottom) = (extreme(1, false), extreme(1, true));
+        Some(Rect {
+            col : self.origin.0 + left,
+            row : self.origin.1 + top,
+            width : right - left + 1,
+            height : bottom - top + 1,
+        })
+    }
+
+    fn step(&mut self, exponent : u32) -> Result<(), String> {
+        engine::check_step_exponent(exponent, engine::MAX_STEP_EXPONENT)?;
+
+        // The result is the central half of the root, so the pattern has to
+        // stay clear of its border for 2^exponent generations. Expanding
+        // leaves the pattern as it is if the universe gets too large.
+        while self.node(self.root).level < (exponent + 2).max(LEAF_LEVEL + 2) || !self.is_centered() {
+            self.expand()?;
+        }
+        self.expand()?;
+
+        let level = self.node(self.root).level;
+        self.root = self.advance(self.root, exponent);
+        let quarter = 1 << (level - 2);
+        self.origin = (self.origin.0 + quarter, self.origin.1 + quarter);
+        self.collect_garbage_if_full();
+        Ok(())
+    }
+
+    fn max_step_exponent(&self) -> u32 {
+        engine::MAX_STEP_EXPONENT
+    }
+
+    fn state_hash(&self) -> u64 {
+        self.node(self.root).hash
+            .wrapping_mul(wrapping_pow(HASH_COL, self.origin.0 as u64))
+            .wrapping_mul(wrapping_pow(HASH_ROW, self.origin.1 as u64))
+    }
+
+    fn fill_words(&self, area : Rect, words : &mut [u16]) {
+        words.iter_mut().for_each(|w| *w = 0);
+        self.fill(self.root, self.origin, area, words);
+    }
+}
@@ -0,0 +1,130 @@
+//! Running a simulation without a window.
+
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use crate::simulation::Simulation;
+
+/// Condition that ends a headless run before the generation limit.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Until {
+    /// No alive cells are left
+    Extinct,
+    /// The board repeats an earlier generation, i.e. it only consists of
+    /// still lifes and oscillators
+    Stable,
+}
+
+impl FromStr for Until {
+    type Err = String;
+
+    fn from_str(s : &str) -> Result<Until, String> {
+        match s {
+            "extinct" => Ok(Until::Extinct),
+            "stable" => Ok(Until::Stable),
+            _ => Err(format!("expected \"extinct\" or \"stable\", found \"{}\"", s)),
+        }
+    }
+}
+
+pub struct Options {
+    /// Maximum number of generations to simulate
+    pub generations : u64,
+    pub until : Option<Until>,
+    /// Print statistics every n generations, 0 only prints the final ones
+    pub stats_interval : u64,
+    /// RLE file the final board is written to
+    pub output : Option<PathBuf>,
+}
+
+pub struct Report {
+    pub generation : u64,
+    pub population : u64,
+    /// Period of the final board if `Until::Stable` was reached
+    pub period : Option<u64>,
+    pub elapsed : Duration,
+}
+
+impl Report {
+    fn print(&self, first_generation : u64) {
+        let gens_per_sec = (self.generation - first_generation) as f64 /
+                           self.elapsed.as_secs_f64().max(1e-9);
+        println!("generation: {:>10}  population: {:>10}  {:.1} gens/s",
+                 self.generation, self.population, gens_per_sec);
+    }
+}
+
+/// Simulates `sim` without a window until at least `options.generations`
+/// further generations are done or the `until` condition is reached. The
+/// generation count advances by the simulation's step size, periods are
+/// found as multiples of it. Fails if the engine can't step the pattern
+/// any further or the final board can't be written.
+pub fn run(sim : &mut Simulation, options : &Options) -> Result<Report, String> {
+    if let Some(seed) = sim.seed() {
+        println!("seed: {}", seed);
+    }
+    let start = Instant::now();
+    let first_generation = sim.generation();
+    let end = first_generation + options.generations;
+    // Repetitions are found with Brent's cycle detection: the board is
+    // compared to a saved one, which is replaced by the current board
+    // after 1, 2, 4, ... steps. This keeps a single hash instead of the
+    // whole history, at the cost of noticing a cycle up to twice as late.
+    let mut saved = (sim.engine().state_hash(), sim.generation());
+    let mut steps_since_saved = 0_u64;
+    let mut save_after = 1_u64;
+
+    let mut report = Report {
+        generation : sim.generation(),
+        population : sim.engine().population(),
+        period : None,
+        elapsed : Duration::default(),
+    };
+
+    while report.generation < end {
+        let previous = report.generation;
+        sim.step()?;
+        report.generation = sim.generation();
+        report.population = sim.engine().population();
+        report.elapsed = start.elapsed();
+
+        // Steps may skip over the multiples of the interval
+        if options.stats_interval > 0 &&
+           report.generation / options.stats_interval > previous / options.stats_interval {
+            report.print(first_generation);
+        }
+
+        match options.until {
+            Some(Until::Extinct) if report.population == 0 => break,
+            Some(Until::Stable) => {
+                let hash = sim.engine().state_hash();
+                if hash == saved.0 {
+                    report.period = Some(report.generation - saved.1);
+                    break;
+                }
+                steps_since_saved += 1;
+                if steps_since_saved == save_after {
+                    saved = (hash, report.generation);
+                    steps_since_saved = 0;
+                    save_after *= 2;
+                }
+            },
+            _ => (),
+        }
+    }
+
+    report.elapsed = start.elapsed();
+    println!("Finished after {:.3}s:", report.elapsed.as_secs_f64());
+    report.print(first_generation);
+    if let Some(period) = report.period {
+        println!("Board became stable with period {}", period);
+    }
+
+    if let Some(filepath) = &options.output {
+        sim.save_rle(filepath)
+           .map_err(|err| format!("Error writing RLE file \"{}\": {}", filepath.display(), err))?;
+    }
+
+    Ok(report)
+}
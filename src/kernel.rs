@@ -0,0 +1,183 @@
+//! Bit sliced evaluation of Life-like rules on 64 cells at once, or on
+//! several words of 64 cells with the vector types of `simd`. Isotropic
+//! non-totalistic rules are looked up cell by cell instead.
+
+use crate::rule::Rule;
+
+/// Words of 64 cells the kernel operates on, either a single `u64` or a
+/// vector of several of them.
+pub(crate) trait Lanes : Copy {
+    fn splat(word : u64) -> Self;
+    fn and(self, other : Self) -> Self;
+    fn or(self, other : Self) -> Self;
+    fn xor(self, other : Self) -> Self;
+    /// `self & !other`
+    fn and_not(self, other : Self) -> Self;
+    /// `(center >> 1) | (left << 63)` in every lane
+    fn west(left : Self, center : Self) -> Self;
+    /// `(center << 1) | (right >> 63)` in every lane
+    fn east(center : Self, right : Self) -> Self;
+}
+
+impl Lanes for u64 {
+    #[inline(always)]
+    fn splat(word : u64) -> u64 { word }
+    #[inline(always)]
+    fn and(self, other : u64) -> u64 { self & other }
+    #[inline(always)]
+    fn or(self, other : u64) -> u64 { self | other }
+    #[inline(always)]
+    fn xor(self, other : u64) -> u64 { self ^ other }
+    #[inline(always)]
+    fn and_not(self, other : u64) -> u64 { self & !other }
+    #[inline(always)]
+    fn west(left : u64, center : u64) -> u64 { (center >> 1) | (left << 63) }
+    #[inline(always)]
+    fn east(center : u64, right : u64) -> u64 { (center << 1) | (right >> 63) }
+}
+
+/// Sum and carry of three bits in each bit position.
+#[inline(always)]
+fn full_add<L : Lanes>(a : L, b : L, c : L) -> (L, L) {
+    let ab = a.xor(b);
+    (ab.xor(c), a.and(b).or(ab.and(c)))
+}
+
+/// Next state of 64 cells given the 8 words holding each cell's
+/// neighbours at the cell's bit position. `transitions` is laid out as
+/// returned by `Rule::transitions`.
+#[inline(always)]
+pub(crate) fn next_cells<L : Lanes>(neighbours : &[L; 8], alive : L, transitions : u32) -> L {
+    // Cells outside of a von Neumann or hexagonal neighbourhood don't count
+    let ignored = transitions >> 18;
+    let mut neighbours = *neighbours;
+    for (i, n) in neighbours.iter_mut().enumerate() {
+        if (ignored >> i) & 1 == 1 {
+            *n = L::splat(0);
+        }
+    }
+    let [n0, n1, n2, n3, n4, n5, n6, n7] = neighbours;
+
+    // Adder tree summing the eight neighbours into the bits of the count
+    let (ones_a, twos_a) = full_add(n0, n1, n2);
+    let (ones_b, twos_b) = full_add(n3, n4, n5);
+    let (ones_c, twos_c) = (n6.xor(n7), n6.and(n7));
+    let (bit0, twos_d) = full_add(ones_a, ones_b, ones_c);
+    let (twos_e, fours_a) = full_add(twos_a, twos_b, twos_c);
+    let (bit1, fours_b) = (twos_e.xor(twos_d), twos_e.and(twos_d));
+    let (bit2, bit3) = (fours_a.xor(fours_b), fours_a.and(fours_b));
+    let bits = [bit0, bit1, bit2, bit3];
+
+    let mut next = L::splat(0);
+    for count in 0..=8 {
+        let birth = (transitions >> count) & 1 == 1;
+        let survival = (transitions >> (count + 9)) & 1 == 1;
+        if !birth && !survival {
+            continue;
+        }
+        let matches = bits.iter().enumerate().fold(L::splat(u64::MAX), |acc, (i, bit)| {
+            if (count >> i) & 1 == 1 { acc.and(*bit) } else { acc.and_not(*bit) }
+        });
+        next = next.or(match (birth, survival) {
+            (true, true) => matches,
+            (true, false) => matches.and_not(alive),
+            _ => matches.and(alive),
+        });
+    }
+    next
+}
+
+/// Neighbours of the 64 cells of `center`, whose rows above and below are
+/// `above` and `below`. Each row is given as the word left of it, the word
+/// itself and the word right of it, the most significant bit being the
+/// leftmost cell.
+#[inline(always)]
+pub(crate) fn neighbours<L : Lanes>(above : [L; 3], center : [L; 3], below : [L; 3]) -> [L; 8] {
+    let west = |[l, c, _] : [L; 3]| L::west(l, c);
+    let east = |[_, c, r] : [L; 3]| L::east(c, r);
+    [west(above), above[1], east(above),
+     west(center), east(center),
+     west(below), below[1], east(below)]
+}
+
+/// What the kernel evaluates for a rule.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Transitions {
+    /// Outer totalistic rules as given by `Rule::transitions`
+    Totalistic(u32),
+    /// Any other rule as given by `Rule::table`
+    Table([u64; 8]),
+}
+
+impl Transitions {
+    pub(crate) fn of(rule : &Rule) -> Transitions {
+        if rule.is_totalistic() {
+            Transitions::Totalistic(rule.transitions())
+        } else {
+            Transitions::Table(rule.table())
+        }
+    }
+}
+
+/// Next state of 64 cells looked up one by one in `table`, the rows given
+/// as for `neighbours`.
+#[inline]
+pub(crate) fn next_cells_table(above : [u64; 3], center : [u64; 3], below : [u64; 3], table : &[u64; 8]) -> u64 {
+    // The word with the cell left of it in bit 65 and the one right of it in bit 0
+    let wide = |[l, c, r] : [u64; 3]| ((l as u128 & 1) << 65) | ((c as u128) << 1) | (r >> 63) as u128;
+    let (above, center, below) = (wide(above), wide(center), wide(below));
+    if above | center | below == 0 {
+        return if table[0] & 1 == 1 { u64::MAX } else { 0 };
+    }
+    let mut next = 0;
+    for bit in 0..64 {
+        let window = |row : u128| ((row >> bit) & 0b111) as usize;
+        let neighbourhood = (window(above) << 6) | (window(center) << 3) | window(below);
+        next |= ((table[neighbourhood / 64] >> (neighbourhood % 64)) & 1) << bit;
+    }
+    next
+}
+
+/// Next state of the 64 cells of `center`, the rows given as for
+/// `neighbours`.
+#[inline(always)]
+pub(crate) fn next_word(above : [u64; 3], center : [u64; 3], below : [u64; 3], transitions : &Transitions) -> u64 {
+    match transitions {
+        Transitions::Totalistic(transitions) => next_cells(&neighbours(above, center, below), center[1], *transitions),
+        Transitions::Table(table) => next_cells_table(above, center, below, table),
+    }
+}
+
+/// Number of bit planes holding the age of the dying cells of a rule with
+/// `states` states: ages run from 1 in state 2 up to `states - 2`.
+pub(crate) fn decay_planes(states : u16) -> usize {
+    (u16::BITS - states.saturating_sub(2).leading_zeros()) as usize
+}
+
+/// Applies the dying states of a Generations rule to 64 cells. `alive` and
+/// `next_alive` are the cells of the current and the next generation as
+/// computed for the Life-like part of the rule, plane `i` of `decay` holds
+/// bit `i` of the age of each dying cell. Writes the ages of the next
+/// generation to `next_decay` and returns its alive cells.
+#[inline]
+pub(crate) fn next_decay(alive : u64, next_alive : u64, decay : &[u64], next_decay : &mut [u64], states : u16) -> u64 {
+    let dying = decay.iter().fold(0, |acc, plane| acc | plane);
+    // Dying cells can't be born
+    let next_alive = next_alive & !dying;
+    let last_age = (states - 2) as usize;
+    let dies = decay.iter().enumerate().fold(dying, |acc, (i, plane)| {
+        acc & if (last_age >> i) & 1 == 1 { *plane } else { !*plane }
+    });
+
+    // Ripple carry adding one to the age of the cells that keep dying
+    let mut carry = dying & !dies;
+    for (next, plane) in next_decay.iter_mut().zip(decay) {
+        *next = (plane ^ carry) & dying & !dies;
+        carry &= plane;
+    }
+    // Cells that don't survive start dying at age 1
+    if let Some(first) = next_decay.first_mut() {
+        *first |= alive & !next_alive;
+    }
+    next_alive
+}
@@ -0,0 +1,49 @@
+//! Game of Life simulation library.
+//!
+//! The board is a bit packed [`grid::Grid`] stepped by a parallel kernel
+//! for any Life-like [`rule::Rule`], huge patterns and long runs are
+//! handled by the [`hashlife::HashLife`] engine and the unbounded plane
+//! by [`sparse::SparseGrid`]; all of them implement [`engine::Engine`].
+//! Only the grid steps Generations, Larger than Life and the Golly rule
+//! tables of [`table`]. The grid kernel runs on the widest
+//! [`simd::Backend`] of the CPU. Patterns are read and written in the RLE
+//! format by [`file_reader`] and [`file_writer`], reproducible random
+//! soups are generated by [`soup`], and a [`Simulation`] ties a board to
+//! its generation count. [`headless`] runs a simulation without a window,
+//! [`speed`] paces it in the viewer and [`worker`] runs it on a
+//! background thread.
+//!
+//! ```no_run
+//! use rusty_life::{Config, Simulation};
+//!
+//! let config = Config {
+//!     rule : "B36/S23".parse().unwrap(),
+//!     seed : Some(42),
+//!     ..Config::default()
+//! };
+//! let mut sim = Simulation::new(&config).unwrap();
+//! for _ in 0..100 {
+//!     sim.step().unwrap();
+//! }
+//! println!("population: {}", sim.engine().population());
+//! ```
+
+pub mod engine;
+pub mod grid;
+pub mod hashlife;
+pub mod sparse;
+mod ltl;
+pub mod simd;
+pub mod rule;
+pub mod table;
+pub mod file_reader;
+pub mod file_writer;
+pub mod soup;
+pub mod simulation;
+pub mod speed;
+pub mod worker;
+pub mod headless;
+
+mod kernel;
+
+pub use simulation::{Config, Simulation, SimulationError};
@@ -0,0 +1,132 @@
+//! Larger than Life: counting the alive cells within a radius of up to
+//! `rule::MAX_RADIUS` around every cell. The counts are read from running
+//! sums: a Moore neighbourhood from a summed-area table at the same cost
+//! for any radius, the other neighbourhoods row by row from the sums along
+//! each row.
+
+use rayon::prelude::*;
+
+use crate::rule::{LargerThanLife, Neighbourhood};
+
+/// Alive cells around every cell of a board, refilled every generation.
+#[derive(Clone)]
+pub(crate) struct Counts {
+    rule : LargerThanLife,
+    width : usize,
+    height : usize,
+    /// Number of alive cells above and left of every position of the board
+    /// extended by `radius` cells on each side, with an extra zero row and
+    /// column in front
+    sums : Vec<u32>,
+    /// Number of alive cells left of every position in the same row, only
+    /// kept for neighbourhoods other than Moore
+    row_sums : Vec<u32>,
+    stride : usize,
+    /// First and last column of each row of the neighbourhood relative to
+    /// the cell, from `radius` rows above the cell to `radius` rows below it
+    spans : Vec<(isize, isize)>,
+}
+
+impl Counts {
+    /// Empty counts for a `width` x `height` board, see `fill`.
+    pub(crate) fn new(rule : LargerThanLife, width : usize, height : usize) -> Counts {
+        let radius = rule.radius as usize;
+        let stride = width + 2 * radius + 1;
+        let sums = vec![0_u32; stride * (height + 2 * radius + 1)];
+        let row_sums = if rule.neighbourhood == Neighbourhood::Moore { Vec::new() } else { sums.clone() };
+
+        let r = radius as isize;
+        let spans = (-r..=r).map(|dy| {
+            let half = |width : isize| (-width, width);
+            match rule.neighbourhood {
+                Neighbourhood::Moore => half(r),
+                Neighbourhood::VonNeumann => half(r - dy.abs()),
+                // Cells whose distance is at most radius + 1/2
+                Neighbourhood::Circular => half((0..=r).rev().find(|dx| dx * dx + dy * dy <= r * r + r).unwrap_or(0)),
+                // Rows above the cell lean left and rows below it right
+                Neighbourhood::Hexagonal => ((dy - r).max(-r), (dy + r).min(r)),
+            }
+        }).collect();
+
+        Counts { rule, width, height, sums, row_sums, stride, spans }
+    }
+
+    /// Whether the counts are made for `rule` on a `width` x `height` board.
+    pub(crate) fn fits(&self, rule : LargerThanLife, width : usize, height : usize) -> bool {
+        self.rule == rule && self.width == width && self.height == height
+    }
+
+    /// Counts the cells of the board, including those at positions outside
+    /// of it, given by `alive`. The buffers are reused every generation.
+    pub(crate) fn fill<F>(&mut self, parallel : bool, alive : F)
+        where F : Fn(isize, isize) -> bool + Sync {
+        let radius = self.rule.radius as isize;
+        let stride = self.stride;
+
+        // Prefix sums of every row first, they are independent of each other
+        let row_sums = |(y, sums) : (usize, &mut [u32])| {
+            let row = y as isize - 1 - radius;
+            let mut sum = 0;
+            for (x, s) in sums.iter_mut().enumerate().skip(1) {
+                sum += alive(x as isize - 1 - radius, row) as u32;
+                *s = sum;
+            }
+        };
+        if parallel {
+            self.sums.par_chunks_mut(stride).enumerate().skip(1).for_each(row_sums);
+        } else {
+            self.sums.chunks_mut(stride).enumerate().skip(1).for_each(row_sums);
+        }
+        if !self.row_sums.is_empty() {
+            self.row_sums.copy_from_slice(&self.sums);
+        }
+        for y in 2..self.sums.len() / stride {
+            let (above, rest) = self.sums.split_at_mut(y * stride);
+            for (s, a) in rest[..stride].iter_mut().zip(&above[(y - 1) * stride..]) {
+                *s += a;
+            }
+        }
+    }
+
+    /// Alive cells in columns `left..=right` and rows `top..=bottom`, given
+    /// in board coordinates.
+    #[inline]
+    fn rectangle(&self, left : isize, right : isize, top : isize, bottom : isize) -> u32 {
+        let radius = self.rule.radius as isize;
+        let at = |x : isize, y : isize| self.sums[(y + radius) as usize * self.stride + (x + radius) as usize];
+        (at(right + 1, bottom + 1) + at(left, top)) - (at(left, bottom + 1) + at(right + 1, top))
+    }
+
+    /// Alive cells in the neighbourhood of a cell, the cell itself included.
+    #[inline]
+    fn count(&self, col : isize, row : isize) -> u32 {
+        let radius = self.rule.radius as isize;
+        if self.rule.neighbourhood == Neighbourhood::Moore {
+            return self.rectangle(col - radius, col + radius, row - radius, row + radius);
+        }
+        // Sums of the columns col + first..=col + last of each row
+        let centre = col + radius;
+        self.spans.iter().enumerate().map(|(dy, (first, last))| {
+            let row_sums = &self.row_sums[(row as usize + 1 + dy) * self.stride..];
+            row_sums[(centre + last + 1) as usize] - row_sums[(centre + first) as usize]
+        }).sum()
+    }
+
+    /// Writes the next alive cells of `row`, whose current cells are
+    /// `current`, to `out`. Both are packed like `Grid::cells`.
+    pub(crate) fn next_row(&self, row : usize, current : &[u64], out : &mut [u64]) {
+        let (birth, survival) = (self.rule.birth, self.rule.survival);
+        for (col_c, (word, next)) in current.iter().zip(out.iter_mut()).enumerate() {
+            *next = 0;
+            for col in col_c * 64..(col_c * 64 + 64).min(self.width) {
+                let bit = 63 - col % 64;
+                let alive = (word >> bit) & 1 == 1;
+                let count = self.count(col as isize, row as isize) - (alive && !self.rule.middle) as u32;
+                let (min, max) = if alive { survival } else { birth };
+                if (min..=max).contains(&count) {
+                    *next |= 1 << bit;
+                }
+            }
+        }
+    }
+}
@@ -1,8 +1,188 @@
-mod rusty_life;
+#[cfg(feature = "viewer")]
+mod viewer;
+
+use clap::Parser;
+use std::path::PathBuf;
+
+/// A Game of Life simulation rendered with OpenGL or run headless.
+#[derive(Parser)]
+#[command(version, about)]
+struct Args {
+    /// Number of cells of the board as WIDTHxHEIGHT
+    #[arg(long, value_name = "WIDTHxHEIGHT", default_value = "1000x1000", value_parser = parse_size::<u32>)]
+    board_size : (u32, u32),
+
+    /// Size of the window in pixels as WIDTHxHEIGHT
+    #[arg(long, value_name = "WIDTHxHEIGHT", default_value = "1920x1080", value_parser = parse_size::<u32>)]
+    window_size : (u32, u32),
+
+    /// RLE file to load instead of a random soup
+    #[arg(long, value_name = "FILE")]
+    pattern : Option<PathBuf>,
+
+    /// Board cell the pattern's top left corner is placed at as COL,ROW
+    #[arg(long, value_name = "COL,ROW", default_value = "0,0", value_parser = parse_offset)]
+    offset : (usize, usize),
+
+    /// Life-like rule in B/S notation, e.g. B36/S23, or the name or path of a
+    /// Golly .rule file
+    #[arg(long, default_value = "B3/S23")]
+    rule : rusty_life::rule::Rule,
+
+    /// How the edges of the board are glued: torus, plane, klein or cross
+    #[arg(long, default_value = "torus")]
+    topology : rusty_life::grid::Topology,
+
+    /// Seed of the random soup
+    #[arg(long)]
+    seed : Option<u64>,
+
+    /// Fraction of the soup's area that is randomly set alive
+    #[arg(long, default_value_t = 0.5, value_parser = parse_density)]
+    fill_density : f64,
+
+    /// Symmetry of the random soup as in apgsearch, e.g. C1, C2_4, D4_+1 or D8_1
+    #[arg(long, default_value = "C1")]
+    symmetry : rusty_life::soup::Symmetry,
+
+    /// Area of the board the random soup is placed in, the whole board if not given
+    #[arg(long, value_name = "COL,ROW,WIDTH,HEIGHT", value_parser = parse_region)]
+    soup_region : Option<rusty_life::grid::Region>,
+
+    /// Algorithm stepping the pattern: "grid", "hashlife" for huge patterns and long runs
+    /// or "sparse" for an unbounded plane
+    #[arg(long, default_value = "grid")]
+    engine : rusty_life::engine::EngineKind,
+
+    /// Every step advances the pattern by 2^N generations, N is at most 16 except for "hashlife"
+    #[arg(long, value_name = "N", default_value_t = 0, value_parser = clap::value_parser!(u32).range(0..=rusty_life::engine::MAX_STEP_EXPONENT as i64))]
+    step_exponent : u32,
+
+    /// Number of threads stepping the pattern, one per core by default.
+    /// With 1 the simulation runs single-threaded, e.g. for profiling
+    #[arg(long, value_name = "N")]
+    threads : Option<std::num::NonZeroUsize>,
+
+    /// Pace of the viewer: a delay between steps like "10ms", steps per
+    /// frame like "4/frame" or generations per second like "1000/s"
+    #[arg(long, default_value = "10ms")]
+    speed : rusty_life::speed::Speed,
+
+    /// Number of generations the G key advances the viewer by
+    #[arg(long, value_name = "N", default_value_t = 1000)]
+    step_by : u64,
+
+    /// Start with the simulation paused
+    #[arg(long)]
+    paused : bool,
+
+    /// Simulate without a window and print statistics to stdout
+    #[arg(long)]
+    headless : bool,
+
+    /// Number of generations to simulate in headless mode
+    #[arg(long, default_value_t = 1000)]
+    generations : u64,
+
+    /// Stop the headless run early once the board is "extinct" or "stable"
+    #[arg(long)]
+    until : Option<rusty_life::headless::Until>,
+
+    /// Print statistics every N generations in headless mode, 0 prints only the final ones
+    #[arg(long, value_name = "N", default_value_t = 100)]
+    stats_every : u64,
+
+    /// RLE file the final board of a headless run is written to
+    #[arg(long, value_name = "FILE")]
+    output : Option<PathBuf>,
+}
+
+fn parse_pair<T>(s : &str, separator : char) -> Result<(T, T), String>
+    where T: std::str::FromStr {
+    let invalid = || format!("expected two numbers separated by '{}', found \"{}\"", separator, s);
+    let mut it = s.split(separator);
+    match (it.next(), it.next(), it.next()) {
+        (Some(a), Some(b), None) => match (a.trim().parse(), b.trim().parse()) {
+            (Ok(a), Ok(b)) => Ok((a, b)),
+            _ => Err(invalid()),
+        },
+        _ => Err(invalid()),
+    }
+}
+
+fn parse_size<T>(s : &str) -> Result<(T, T), String>
+    where T: std::str::FromStr {
+    parse_pair(s, 'x')
+}
+
+fn parse_offset(s : &str) -> Result<(usize, usize), String> {
+    parse_pair(s, ',')
+}
+
+fn parse_region(s : &str) -> Result<rusty_life::grid::Region, String> {
+    let numbers = s.split(',').map(|n| n.trim().parse::<usize>()).collect::<Vec<_>>();
+    match numbers.as_slice() {
+        [Ok(col), Ok(row), Ok(width), Ok(height)] => Ok(rusty_life::grid::Region {
+            col : *col,
+            row : *row,
+            width : *width,
+            height : *height,
+        }),
+        _ => Err(format!("expected four numbers COL,ROW,WIDTH,HEIGHT, found \"{}\"", s)),
+    }
+}
+
+fn parse_density(s : &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(d) if (0.0..=1.0).contains(&d) => Ok(d),
+        _ => Err(format!("expected a number between 0 and 1, found \"{}\"", s)),
+    }
+}
 
 fn main() {
-    let mut gof = rusty_life::RustyLife::new((1000, 1000),
-                                             "Rusty Life",
-                                             (1920, 1080));
-    gof.run();
-}
\ No newline at end of file
+    let args = Args::parse();
+
+    let config = rusty_life::Config {
+        board_size : args.board_size,
+        rule : args.rule,
+        topology : args.topology,
+        pattern : args.pattern,
+        pattern_offset : args.offset,
+        seed : args.seed,
+        fill_density : args.fill_density,
+        symmetry : args.symmetry,
+        soup_region : args.soup_region,
+        engine : args.engine,
+        step_exponent : args.step_exponent,
+        threads : args.threads,
+    };
+
+    let mut sim = match rusty_life::Simulation::new(&config) {
+        Ok(sim) => sim,
+        Err(err) => {
+            eprintln!("Error creating the simulation: \n\t{}", err);
+            std::process::exit(1);
+        }
+    };
+    if let Some(replaced) = sim.replaced_rule() {
+        println!("The pattern is meant for rule {}, switching from {}", sim.rule(), replaced);
+    }
+
+    #[cfg(feature = "viewer")]
+    if !args.headless {
+        let mut gof = viewer::RustyLife::new("Rusty Life", args.window_size, args.paused, args.speed, args.step_by, sim);
+        gof.run();
+        return;
+    }
+
+    let options = rusty_life::headless::Options {
+        generations : args.generations,
+        until : args.until,
+        stats_interval : args.stats_every,
+        output : args.output,
+    };
+    if let Err(err) = rusty_life::headless::run(&mut sim, &options) {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,558 @@
+//! Life-like rules in B/S notation, optionally isotropic non-totalistic in
+//! Hensel notation, and their multi-state Generations variants in B/S/C
+//! notation, as well as Larger than Life rules counting the cells within a
+//! radius and multi-state rules given by a `RuleTable`.
+
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::table::RuleTable;
+
+/// Well-known Life-like rules that can be cycled through at runtime.
+pub const PRESETS : [(&str, &str); 15] = [
+    ("Conway's Life", "B3/S23"),
+    ("HighLife", "B36/S23"),
+    ("Day & Night", "B3678/S34678"),
+    ("Seeds", "B2/S"),
+    ("Maze", "B3/S12345"),
+    ("Life without Death", "B3/S012345678"),
+    ("Diamoeba", "B35678/S5678"),
+    ("2x2", "B36/S125"),
+    ("Morley", "B368/S245"),
+    ("Replicator", "B1357/S1357"),
+    ("tlife", "B3/S2-i34q"),
+    ("Brian's Brain", "/2/3"),
+    ("Star Wars", "345/2/4"),
+    ("Bosco's Rule", "R5,C0,M1,S34..58,B34..45,NM"),
+    ("Majority", "R4,C0,M1,S41..81,B41..81,NM"),
+];
+
+/// Letters of the configurations of `n` alive neighbours in Hensel
+/// notation, in the order of `CONFIGURATIONS`.
+const LETTERS : [&str; 9] = ["", "ce", "cekain", "cekainyqjr", "cekainyqjrtwz", "cekainyqjr", "cekain", "ce", ""];
+
+/// One neighbourhood of each configuration of up to 4 alive neighbours,
+/// indexed like `Rule::table`. The configurations of `n > 4` neighbours
+/// are the complements of those of `8 - n`.
+const CONFIGURATIONS : [&[u16]; 5] = [
+    &[0],
+    &[1, 2],
+    &[5, 10, 33, 3, 40, 68],
+    &[69, 42, 98, 11, 7, 13, 97, 70, 14, 41],
+    &[325, 170, 99, 15, 45, 71, 101, 102, 106, 43, 105, 78, 108],
+];
+
+/// Bit of the centre cell in a neighbourhood.
+const CENTRE : u16 = 1 << 4;
+
+/// The neighbourhood rotated by 90 degrees.
+const fn rotate(neighbourhood : u16) -> u16 {
+    let mut rotated = 0;
+    let mut bit = 0;
+    while bit < 9 {
+        let (row, col) = (2 - bit / 3, 2 - bit % 3);
+        if neighbourhood & (1 << bit) != 0 {
+            rotated |= 1 << (8 - (3 * col + (2 - row)));
+        }
+        bit += 1;
+    }
+    rotated
+}
+
+/// The neighbourhood mirrored left to right.
+const fn mirror(neighbourhood : u16) -> u16 {
+    let mut mirrored = 0;
+    let mut row = 0;
+    while row < 3 {
+        let bits = (neighbourhood >> (3 * row)) & 0b111;
+        mirrored |= (((bits & 1) << 2) | (bits & 2) | (bits >> 2)) << (3 * row);
+        row += 1;
+    }
+    mirrored
+}
+
+/// Whether a rotation or reflection turns one neighbourhood into the other.
+const fn symmetric(a : u16, b : u16) -> bool {
+    let mut a = a;
+    let mut i = 0;
+    while i < 4 {
+        if a == b || mirror(a) == b {
+            return true;
+        }
+        a = rotate(a);
+        i += 1;
+    }
+    false
+}
+
+/// Index into `LETTERS[n]` of the configuration of every neighbourhood of
+/// `n` alive neighbours, ignoring the centre cell.
+const LETTER_OF : [u8; 512] = letter_of();
+
+const fn letter_of() -> [u8; 512] {
+    let mut letters = [0; 512];
+    let mut neighbourhood = 0;
+    while neighbourhood < 512 {
+        let cells = neighbourhood as u16 & !CENTRE;
+        let n = cells.count_ones() as usize;
+        let complement = n > 4;
+        let representatives = CONFIGURATIONS[if complement { 8 - n } else { n }];
+        let mut letter = 0;
+        while letter < representatives.len() {
+            let representative = representatives[letter];
+            let representative = if complement { !representative & 0x1ff & !CENTRE } else { representative };
+            if symmetric(representative, cells) {
+                letters[neighbourhood] = letter as u8;
+            }
+            letter += 1;
+        }
+        neighbourhood += 1;
+    }
+    letters
+}
+
+/// Mask of all configurations of `n` alive neighbours.
+const fn all_configurations(n : usize) -> u16 {
+    if LETTERS[n].is_empty() { 1 } else { (1 << LETTERS[n].len()) - 1 }
+}
+
+/// Configurations of a rule in which every one of the counts in `mask` is
+/// included as a whole.
+fn totalistic(mask : u16) -> [u16; 9] {
+    let mut conditions = [0; 9];
+    for (n, c) in conditions.iter_mut().enumerate() {
+        if mask & (1 << n) != 0 {
+            *c = all_configurations(n);
+        }
+    }
+    conditions
+}
+
+/// Neighbourhood of a rule. Rules on the 3x3 neighbourhood use the Moore
+/// neighbourhood unless their rulestring ends in "V" or "H", Larger than
+/// Life rules any of them with a radius.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Neighbourhood {
+    /// The square of cells at most `radius` columns and rows away, "NM"
+    Moore,
+    /// The diamond of cells at most `radius` steps along rows and columns
+    /// away, "NN" or "V"
+    VonNeumann,
+    /// The cells within a distance of `radius + 1/2`, "NC"
+    Circular,
+    /// The hexagon of cells at most `radius` steps away on a hexagonal
+    /// grid sheared onto the square one, which leaves out the cells up and
+    /// right and down and left of the cell, "NH" or "H"
+    Hexagonal,
+}
+
+impl Neighbourhood {
+    /// Largest number of neighbours on the 3x3 neighbourhood.
+    fn size(self) -> usize {
+        match self {
+            Neighbourhood::VonNeumann => 4,
+            Neighbourhood::Hexagonal => 6,
+            Neighbourhood::Moore | Neighbourhood::Circular => 8,
+        }
+    }
+}
+
+/// Largest radius of a Larger than Life rule.
+pub const MAX_RADIUS : u32 = 500;
+
+/// A Larger than Life rule in the notation "R5,C0,M1,S34..58,B34..45,NM".
+/// An alive cell survives if the number of alive cells in its
+/// neighbourhood lies within `survival`, a dead one is born if it lies
+/// within `birth`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct LargerThanLife {
+    pub radius : u32,
+    /// Whether a cell counts itself, "M1"
+    pub middle : bool,
+    /// Inclusive range of counts
+    pub survival : (u32, u32),
+    /// Inclusive range of counts
+    pub birth : (u32, u32),
+    pub neighbourhood : Neighbourhood,
+}
+
+impl LargerThanLife {
+    /// The rule with `states` states, more than two making dying cells
+    /// decay like in Generations rules.
+    pub fn rule(self, states : u16) -> Rule {
+        Rule { larger_than_life : Some(self), ..Rule::generations(0, 0, states) }
+    }
+}
+
+/// A Life-like rule in B/S notation, either outer totalistic or isotropic
+/// non-totalistic in Hensel notation such as "B2-a/S12".
+///
+/// Bit `k` of `birth[n]` is set if a dead cell with `n` alive neighbours
+/// in configuration `LETTERS[n][k]` becomes alive, bit `k` of `survival[n]`
+/// if an alive cell with such neighbours stays alive. Outer totalistic
+/// rules include all or none of the configurations of each count.
+///
+/// Generations rules have more than two `states`: state 0 is dead, state 1
+/// alive, and an alive cell that doesn't survive passes through the dying
+/// states 2, 3, ... `states - 1` before it is dead again. Dying cells don't
+/// count as neighbours and can't be born.
+///
+/// The counts of outer totalistic rules may refer to the von Neumann or
+/// hexagonal `neighbourhood` instead of the Moore one. Larger than Life
+/// rules replace the 3x3 neighbourhood and the conditions by the
+/// `LargerThanLife` parameters, rule tables by their transitions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    birth : [u16; 9],
+    survival : [u16; 9],
+    states : u16,
+    neighbourhood : Neighbourhood,
+    larger_than_life : Option<LargerThanLife>,
+    table : Option<Arc<RuleTable>>,
+}
+
+/// Largest number of states of a Generations rule.
+pub const MAX_STATES : u16 = 256;
+
+impl Rule {
+    pub fn new(birth : u16, survival : u16) -> Rule {
+        Rule::generations(birth, survival, 2)
+    }
+
+    /// Generations rule with `states` states, clamped to 2..=`MAX_STATES`.
+    pub fn generations(birth : u16, survival : u16, states : u16) -> Rule {
+        Rule {
+            birth : totalistic(birth),
+            survival : totalistic(survival),
+            states : states.clamp(2, MAX_STATES),
+            neighbourhood : Neighbourhood::Moore,
+            larger_than_life : None,
+            table : None,
+        }
+    }
+
+    /// The rule stepping cells by the transitions of `table`.
+    pub fn from_table(table : RuleTable) -> Rule {
+        Rule {
+            states : table.states(),
+            neighbourhood : table.neighbourhood(),
+            table : Some(Arc::new(table)),
+            ..Rule::new(0, 0)
+        }
+    }
+
+    /// Parses the rule declared by a pattern file like `from_str`, but
+    /// doesn't read rule tables from a path. Tables are only found by name,
+    /// see `RuleTable::find`, so a pattern can't open arbitrary files.
+    pub fn from_pattern(s : &str) -> Result<Rule, String> {
+        let s : String = s.split_whitespace().collect();
+        if s.to_ascii_lowercase().ends_with(".rule") {
+            return Err(format!("Invalid rule \"{}\": patterns may only name rule tables, not give their path", s));
+        }
+        s.parse()
+    }
+
+    pub fn conway() -> Rule {
+        Rule::new(1 << 3, (1 << 2) | (1 << 3))
+    }
+
+    /// Transition table consulted by the kernel: bit `n` holds the next
+    /// state of a dead cell with `n` neighbours, bit `9 + n` the next state
+    /// of an alive cell with `n` neighbours. Bit `18 + i` is set if the
+    /// `i`-th of the 8 surrounding cells, counted row by row from the top
+    /// left, isn't part of the neighbourhood. Only complete for outer
+    /// totalistic rules, otherwise counts of which only some configurations
+    /// are included are left out.
+    #[inline]
+    pub fn transitions(&self) -> u32 {
+        let ignored = match self.neighbourhood {
+            Neighbourhood::VonNeumann => 0b1010_0101,
+            Neighbourhood::Hexagonal => 0b0010_0100,
+            Neighbourhood::Moore | Neighbourhood::Circular => 0,
+        };
+        (0..9).filter(|n| self.birth[*n] == all_configurations(*n)).fold(0, |t, n| t | (1 << n)) |
+        (0..9).filter(|n| self.survival[*n] == all_configurations(*n)).fold(0, |t, n| t | (1 << (n + 9))) |
+        (ignored << 18)
+    }
+
+    /// Whether the next state only depends on the number of neighbours and
+    /// not on their configuration, i.e. `transitions` describes the rule.
+    pub fn is_totalistic(&self) -> bool {
+        (0..9).all(|n| {
+            [self.birth[n], self.survival[n]].iter().all(|c| *c == 0 || *c == all_configurations(n))
+        })
+    }
+
+    /// Next state of a cell for each of the 512 states of its 3x3
+    /// neighbourhood. The neighbourhood is read row by row from the top
+    /// left, its first cell being the most significant of 9 bits, and bit
+    /// `i % 64` of word `i / 64` holds the next state for neighbourhood `i`.
+    pub fn table(&self) -> [u64; 8] {
+        let ignored = match self.neighbourhood {
+            Neighbourhood::VonNeumann => 0b101_000_101,
+            Neighbourhood::Hexagonal => 0b001_000_100,
+            Neighbourhood::Moore | Neighbourhood::Circular => 0,
+        };
+        let mut table = [0; 8];
+        for neighbourhood in 0..512 {
+            let n = (neighbourhood as u16 & !CENTRE & !ignored).count_ones() as usize;
+            let conditions = if neighbourhood as u16 & CENTRE != 0 { &self.survival } else { &self.birth };
+            // Only rules on the Moore neighbourhood tell configurations apart
+            let included = if ignored == 0 { (conditions[n] >> LETTER_OF[neighbourhood]) & 1 == 1 } else { conditions[n] != 0 };
+            if included {
+                table[neighbourhood / 64] |= 1 << (neighbourhood % 64);
+            }
+        }
+        table
+    }
+
+    /// Number of states, 2 for Life-like rules.
+    pub fn states(&self) -> u16 {
+        self.states
+    }
+
+    /// Neighbourhood the rule counts the alive cells in.
+    pub fn neighbourhood(&self) -> Neighbourhood {
+        self.larger_than_life.map_or(self.neighbourhood, |ltl| ltl.neighbourhood)
+    }
+
+    /// Parameters of a Larger than Life rule, `None` for rules on the 3x3
+    /// neighbourhood.
+    pub fn larger_than_life(&self) -> Option<LargerThanLife> {
+        self.larger_than_life
+    }
+
+    /// Transitions of a rule given by a table, `None` for other rules.
+    pub fn rule_table(&self) -> Option<&RuleTable> {
+        self.table.as_deref()
+    }
+
+    /// Name of the rule if it is one of the `PRESETS`.
+    pub fn name(&self) -> Option<&'static str> {
+        PRESETS.iter()
+               .find(|(_, r)| r.parse::<Rule>().as_ref() == Ok(self))
+               .map(|(name, _)| *name)
+    }
+}
+
+impl Default for Rule {
+    fn default() -> Rule {
+        Rule::conway()
+    }
+}
+
+/// Parses a Larger than Life rule "R5,C0,M1,S34..58,B34..45,NM". C, M and
+/// N may be left out for 2 states, not counting the middle cell and the
+/// Moore neighbourhood.
+fn parse_larger_than_life(s : &str) -> Result<Rule, String> {
+    let mut rule = LargerThanLife {
+        radius : 0,
+        middle : false,
+        survival : (1, 0),
+        birth : (1, 0),
+        neighbourhood : Neighbourhood::Moore,
+    };
+    let mut states = 2;
+    let (mut has_survival, mut has_birth) = (false, false);
+    let range = |value : &str| -> Result<(u32, u32), String> {
+        let (min, max) = value.split_once("..").ok_or_else(|| format!("expected a range \"min..max\", found \"{}\"", value))?;
+        match (min.parse(), max.parse()) {
+            (Ok(min), Ok(max)) => Ok((min, max)),
+            _ => Err(format!("Invalid range \"{}\"", value)),
+        }
+    };
+    for part in s.split(',') {
+        let mut chars = part.chars();
+        let key = chars.next().map(|c| c.to_ascii_uppercase());
+        let value = chars.as_str();
+        let number = || value.parse::<u32>().map_err(|_| format!("Invalid number \"{}\" in \"{}\"", value, part));
+        match key {
+            Some('R') => rule.radius = number()?,
+            Some('C') => states = number()?.max(2),
+            Some('M') => rule.middle = number()? == 1,
+            Some('S') => { rule.survival = range(value)?; has_survival = true; }
+            Some('B') => { rule.birth = range(value)?; has_birth = true; }
+            Some('N') => rule.neighbourhood = match value.to_ascii_uppercase().as_str() {
+                "M" => Neighbourhood::Moore,
+                "N" => Neighbourhood::VonNeumann,
+                "C" => Neighbourhood::Circular,
+                "H" => Neighbourhood::Hexagonal,
+                _ => return Err(format!("Unknown neighbourhood \"{}\", expected M, N, C or H", value)),
+            },
+            _ => return Err(format!("Unknown parameter \"{}\"", part)),
+        }
+    }
+    if !(1..=MAX_RADIUS).contains(&rule.radius) {
+        return Err(format!("expected a radius of 1 to {}, found {}", MAX_RADIUS, rule.radius));
+    }
+    if !has_survival || !has_birth {
+        return Err("expected ranges for S and B".to_string());
+    }
+    if states > MAX_STATES as u32 {
+        return Err(format!("expected 2 to {} states, found {}", MAX_STATES, states));
+    }
+    Ok(rule.rule(states as u16))
+}
+
+/// Parses neighbour counts, each optionally followed by the letters of
+/// the configurations it is limited to or, after a '-', excludes.
+fn parse_conditions(conditions : &str) -> Result<[u16; 9], String> {
+    let mut parsed = [0; 9];
+    let mut chars = conditions.chars().peekable();
+    while let Some(c) = chars.next() {
+        let n = match c.to_digit(10) {
+            Some(n) if n <= 8 => n as usize,
+            _ => return Err(format!("Invalid neighbour count '{}'", c)),
+        };
+        let negated = chars.next_if_eq(&'-').is_some();
+        let mut letters = 0_u16;
+        while let Some(letter) = chars.next_if(|c| c.is_ascii_alphabetic()) {
+            match LETTERS[n].find(letter.to_ascii_lowercase()) {
+                Some(k) => letters |= 1 << k,
+                None => return Err(format!("Invalid configuration '{}{}'", n, letter)),
+            }
+        }
+        parsed[n] |= match (negated, letters) {
+            (true, 0) => return Err(format!("Missing configurations after '{}-'", n)),
+            (true, _) => all_configurations(n) & !letters,
+            (false, 0) => all_configurations(n),
+            (false, _) => letters,
+        };
+    }
+    Ok(parsed)
+}
+
+/// Writes the counts of `conditions` in Hensel notation, listing whichever
+/// of the included or excluded configurations are fewer.
+fn write_conditions(f : &mut fmt::Formatter, conditions : &[u16; 9]) -> fmt::Result {
+    for (n, included) in conditions.iter().enumerate() {
+        let all = all_configurations(n);
+        if *included == 0 {
+            continue;
+        }
+        write!(f, "{}", n)?;
+        if *included == all {
+            continue;
+        }
+        let (prefix, listed) = if included.count_ones() * 2 <= all.count_ones() {
+            ("", *included)
+        } else {
+            ("-", all & !included)
+        };
+        write!(f, "{}", prefix)?;
+        for (k, letter) in LETTERS[n].chars().enumerate() {
+            if listed & (1 << k) != 0 {
+                write!(f, "{}", letter)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+impl FromStr for Rule {
+    type Err = String;
+
+    /// Parses "B36/S23", "S23/B36" and the traditional survival-first
+    /// "23/36" notation. Each count may be limited to some configurations in
+    /// Hensel notation, "B2-a/S12" or "B3/S23-a4i". Generations rules take
+    /// the number of states as a third part, "B2/S/C3" or survival-first
+    /// "/2/3". A final "V" or "H" counts the von Neumann or the hexagonal
+    /// neighbourhood, "B2/S013V". Larger than Life rules are given as
+    /// "R5,C0,M1,S34..58,B34..45,NM". Letters are case-insensitive.
+    ///
+    /// Rule tables are given by the path of their ".rule" file or by their
+    /// name, "WireWorld", see `RuleTable::find`.
+    fn from_str(s : &str) -> Result<Rule, String> {
+        let s : String = s.split_whitespace().collect();
+        let invalid = |message : String| format!("Invalid rule \"{}\": {}", s, message);
+        if s.starts_with(['R', 'r']) && s.contains(',') {
+            return parse_larger_than_life(&s).map_err(invalid);
+        }
+        if s.to_ascii_lowercase().ends_with(".rule") {
+            return RuleTable::read(&s).map(Rule::from_table).map_err(invalid);
+        }
+        if s.starts_with(|c : char| c.is_ascii_alphabetic()) && !s.contains('/') {
+            return RuleTable::find(&s).map(Rule::from_table).map_err(invalid);
+        }
+        let (rule, neighbourhood) = match s.chars().last().map(|c| c.to_ascii_uppercase()) {
+            Some('V') => (&s[..s.len() - 1], Neighbourhood::VonNeumann),
+            Some('H') => (&s[..s.len() - 1], Neighbourhood::Hexagonal),
+            _ => (s.as_str(), Neighbourhood::Moore),
+        };
+        let parts = rule.split('/').collect::<Vec<_>>();
+        let (first, second, third) = match parts.as_slice() {
+            [a, b] => (*a, *b, None),
+            [a, b, c] => (*a, *b, Some(*c)),
+            _ => return Err(invalid("expected one or two '/'".to_string())),
+        };
+
+        let prefix = |p : &str| p.chars().next().map(|c| c.to_ascii_uppercase());
+        let (birth, survival) = match (prefix(first), prefix(second)) {
+            (Some('B'), Some('S')) => (&first[1..], &second[1..]),
+            (Some('S'), Some('B')) => (&second[1..], &first[1..]),
+            (Some('S'), None) => ("", &first[1..]),
+            (Some('B'), None) => (&first[1..], ""),
+            _ => (second, first),
+        };
+        let states = match third {
+            None => 2,
+            Some(c) => {
+                let c = match prefix(c) {
+                    Some('C') | Some('G') => &c[1..],
+                    _ => c,
+                };
+                match c.parse::<u16>() {
+                    Ok(n) if (2..=MAX_STATES).contains(&n) => n,
+                    _ => return Err(invalid(format!("expected 2 to {} states, found \"{}\"", MAX_STATES, c))),
+                }
+            }
+        };
+
+        let (birth, survival) = match (parse_conditions(birth), parse_conditions(survival)) {
+            (Ok(birth), Ok(survival)) => (birth, survival),
+            (Err(err), _) | (_, Err(err)) => return Err(invalid(err)),
+        };
+        let rule = Rule { birth, survival, neighbourhood, ..Rule::generations(0, 0, states) };
+        if neighbourhood != Neighbourhood::Moore {
+            if let Some(n) = (neighbourhood.size() + 1..9).find(|n| birth[*n] | survival[*n] != 0) {
+                return Err(invalid(format!("the neighbourhood has at most {} neighbours, found {}", neighbourhood.size(), n)));
+            }
+            if !rule.is_totalistic() {
+                return Err(invalid("Hensel notation needs the Moore neighbourhood".to_string()));
+            }
+        }
+        Ok(rule)
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        if let Some(table) = &self.table {
+            return write!(f, "{}", table.name());
+        }
+        if let Some(ltl) = self.larger_than_life {
+            let neighbourhood = match ltl.neighbourhood {
+                Neighbourhood::Moore => "M",
+                Neighbourhood::VonNeumann => "N",
+                Neighbourhood::Circular => "C",
+                Neighbourhood::Hexagonal => "H",
+            };
+            return write!(f, "R{},C{},M{},S{}..{},B{}..{},N{}",
+                          ltl.radius, if self.states > 2 { self.states } else { 0 }, ltl.middle as u8,
+                          ltl.survival.0, ltl.survival.1, ltl.birth.0, ltl.birth.1, neighbourhood);
+        }
+        write!(f, "B")?;
+        write_conditions(f, &self.birth)?;
+        write!(f, "/S")?;
+        write_conditions(f, &self.survival)?;
+        if self.states > 2 {
+            write!(f, "/C{}", self.states)?;
+        }
+        match self.neighbourhood {
+            Neighbourhood::VonNeumann => write!(f, "V"),
+            Neighbourhood::Hexagonal => write!(f, "H"),
+            Neighbourhood::Moore | Neighbourhood::Circular => Ok(()),
+        }
+    }
+}
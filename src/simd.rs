@@ -0,0 +1,258 @@
+//! Vectorized kernel stepping several words of a row at once.
+//!
+//! The bit sliced kernel runs on vectors of 4 words with AVX2 and 8 words
+//! with AVX-512. `Backend::detect` picks the widest one the CPU supports at
+//! runtime; on other CPUs the portable backend works on plain arrays of 4
+//! words, which the compiler vectorizes with whatever the target offers.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::kernel::{self, Lanes};
+
+/// Implementation of the kernel used by a `Grid`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Backend {
+    /// One word at a time
+    Scalar,
+    /// Arrays of 4 words, available everywhere
+    Portable,
+    /// 256 bit AVX2 vectors of 4 words
+    Avx2,
+    /// 512 bit AVX-512 vectors of 8 words
+    Avx512,
+}
+
+impl Backend {
+    /// All backends, from the narrowest to the widest.
+    pub const ALL : [Backend; 4] = [Backend::Scalar, Backend::Portable, Backend::Avx2, Backend::Avx512];
+
+    /// Widest backend supported by the CPU.
+    pub fn detect() -> Backend {
+        *Self::ALL.iter().rev().find(|b| b.is_supported()).unwrap_or(&Backend::Scalar)
+    }
+
+    /// Whether the CPU this runs on supports the backend.
+    pub fn is_supported(self) -> bool {
+        match self {
+            Backend::Scalar | Backend::Portable => true,
+            #[cfg(target_arch = "x86_64")]
+            Backend::Avx2 => is_x86_feature_detected!("avx2"),
+            #[cfg(target_arch = "x86_64")]
+            Backend::Avx512 => is_x86_feature_detected!("avx512f"),
+            #[cfg(not(target_arch = "x86_64"))]
+            Backend::Avx2 | Backend::Avx512 => false,
+        }
+    }
+
+    /// Number of words stepped at once.
+    pub fn lanes(self) -> usize {
+        match self {
+            Backend::Scalar => 1,
+            Backend::Portable | Backend::Avx2 => 4,
+            Backend::Avx512 => 8,
+        }
+    }
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s : &str) -> Result<Backend, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "scalar" => Ok(Backend::Scalar),
+            "portable" => Ok(Backend::Portable),
+            "avx2" => Ok(Backend::Avx2),
+            "avx512" => Ok(Backend::Avx512),
+            _ => Err(format!("expected \"scalar\", \"portable\", \"avx2\" or \"avx512\", found \"{}\"", s)),
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Backend::Scalar => "scalar",
+            Backend::Portable => "portable",
+            Backend::Avx2 => "avx2",
+            Backend::Avx512 => "avx512",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Lanes that can be loaded from and stored to consecutive words.
+trait Vector : Lanes {
+    const LANES : usize;
+    fn load(words : &[u64]) -> Self;
+    fn store(self, words : &mut [u64]);
+}
+
+impl Vector for u64 {
+    const LANES : usize = 1;
+    #[inline(always)]
+    fn load(words : &[u64]) -> u64 { words[0] }
+    #[inline(always)]
+    fn store(self, words : &mut [u64]) { words[0] = self }
+}
+
+type Portable = [u64; 4];
+
+impl Lanes for Portable {
+    #[inline(always)]
+    fn splat(word : u64) -> Portable { [word; 4] }
+    #[inline(always)]
+    fn and(self, other : Portable) -> Portable { std::array::from_fn(|i| self[i] & other[i]) }
+    #[inline(always)]
+    fn or(self, other : Portable) -> Portable { std::array::from_fn(|i| self[i] | other[i]) }
+    #[inline(always)]
+    fn xor(self, other : Portable) -> Portable { std::array::from_fn(|i| self[i] ^ other[i]) }
+    #[inline(always)]
+    fn and_not(self, other : Portable) -> Portable { std::array::from_fn(|i| self[i] & !other[i]) }
+    #[inline(always)]
+    fn west(left : Portable, center : Portable) -> Portable {
+        std::array::from_fn(|i| u64::west(left[i], center[i]))
+    }
+    #[inline(always)]
+    fn east(center : Portable, right : Portable) -> Portable {
+        std::array::from_fn(|i| u64::east(center[i], right[i]))
+    }
+}
+
+impl Vector for Portable {
+    const LANES : usize = 4;
+    #[inline(always)]
+    fn load(words : &[u64]) -> Portable { std::array::from_fn(|i| words[i]) }
+    #[inline(always)]
+    fn store(self, words : &mut [u64]) { words[..4].copy_from_slice(&self) }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use std::arch::x86_64::*;
+
+    use super::Vector;
+    use crate::kernel::Lanes;
+
+    // The intrinsics are only executed from functions compiled with the
+    // matching target feature, into which these methods are inlined
+
+    impl Lanes for __m256i {
+        #[inline(always)]
+        fn splat(word : u64) -> __m256i { unsafe { _mm256_set1_epi64x(word as i64) } }
+        #[inline(always)]
+        fn and(self, other : __m256i) -> __m256i { unsafe { _mm256_and_si256(self, other) } }
+        #[inline(always)]
+        fn or(self, other : __m256i) -> __m256i { unsafe { _mm256_or_si256(self, other) } }
+        #[inline(always)]
+        fn xor(self, other : __m256i) -> __m256i { unsafe { _mm256_xor_si256(self, other) } }
+        #[inline(always)]
+        fn and_not(self, other : __m256i) -> __m256i { unsafe { _mm256_andnot_si256(other, self) } }
+        #[inline(always)]
+        fn west(left : __m256i, center : __m256i) -> __m256i {
+            unsafe { _mm256_or_si256(_mm256_srli_epi64::<1>(center), _mm256_slli_epi64::<63>(left)) }
+        }
+        #[inline(always)]
+        fn east(center : __m256i, right : __m256i) -> __m256i {
+            unsafe { _mm256_or_si256(_mm256_slli_epi64::<1>(center), _mm256_srli_epi64::<63>(right)) }
+        }
+    }
+
+    impl Vector for __m256i {
+        const LANES : usize = 4;
+        #[inline(always)]
+        fn load(words : &[u64]) -> __m256i {
+            unsafe { _mm256_loadu_si256(words[..4].as_ptr() as *const __m256i) }
+        }
+        #[inline(always)]
+        fn store(self, words : &mut [u64]) {
+            unsafe { _mm256_storeu_si256(words[..4].as_mut_ptr() as *mut __m256i, self) }
+        }
+    }
+
+    impl Lanes for __m512i {
+        #[inline(always)]
+        fn splat(word : u64) -> __m512i { unsafe { _mm512_set1_epi64(word as i64) } }
+        #[inline(always)]
+        fn and(self, other : __m512i) -> __m512i { unsafe { _mm512_and_si512(self, other) } }
+        #[inline(always)]
+        fn or(self, other : __m512i) -> __m512i { unsafe { _mm512_or_si512(self, other) } }
+        #[inline(always)]
+        fn xor(self, other : __m512i) -> __m512i { unsafe { _mm512_xor_si512(self, other) } }
+        #[inline(always)]
+        fn and_not(self, other : __m512i) -> __m512i { unsafe { _mm512_andnot_si512(other, self) } }
+        #[inline(always)]
+        fn west(left : __m512i, center : __m512i) -> __m512i {
+            unsafe { _mm512_or_si512(_mm512_srli_epi64::<1>(center), _mm512_slli_epi64::<63>(left)) }
+        }
+        #[inline(always)]
+        fn east(center : __m512i, right : __m512i) -> __m512i {
+            unsafe { _mm512_or_si512(_mm512_slli_epi64::<1>(center), _mm512_srli_epi64::<63>(right)) }
+        }
+    }
+
+    impl Vector for __m512i {
+        const LANES : usize = 8;
+        #[inline(always)]
+        fn load(words : &[u64]) -> __m512i {
+            unsafe { _mm512_loadu_si512(words[..8].as_ptr() as *const __m512i) }
+        }
+        #[inline(always)]
+        fn store(self, words : &mut [u64]) {
+            unsafe { _mm512_storeu_si512(words[..8].as_mut_ptr() as *mut __m512i, self) }
+        }
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub(super) fn next_words_avx2(above : &[u64], center : &[u64], below : &[u64],
+                                  out : &mut [u64], transitions : u32) {
+        super::next_words_with::<__m256i>(above, center, below, out, transitions)
+    }
+
+    #[target_feature(enable = "avx512f")]
+    pub(super) fn next_words_avx512(above : &[u64], center : &[u64], below : &[u64],
+                                    out : &mut [u64], transitions : u32) {
+        super::next_words_with::<__m512i>(above, center, below, out, transitions)
+    }
+}
+
+/// Steps the words with vectors of `V`, the remainder one word at a time.
+#[inline(always)]
+fn next_words_with<V : Vector>(above : &[u64], center : &[u64], below : &[u64],
+                               out : &mut [u64], transitions : u32) {
+    #[inline(always)]
+    fn step<V : Vector>(above : &[u64], center : &[u64], below : &[u64], out : &mut [u64], transitions : u32) {
+        let words = |row : &[u64]| [V::load(row), V::load(&row[1..]), V::load(&row[2..])];
+        let center = words(center);
+        let neighbours = kernel::neighbours(words(above), center, words(below));
+        kernel::next_cells(&neighbours, center[1], transitions).store(out);
+    }
+
+    let vectors = out.len() / V::LANES * V::LANES;
+    for i in (0..vectors).step_by(V::LANES) {
+        step::<V>(&above[i..], &center[i..], &below[i..], &mut out[i..], transitions);
+    }
+    for i in vectors..out.len() {
+        step::<u64>(&above[i..], &center[i..], &below[i..], &mut out[i..], transitions);
+    }
+}
+
+/// Next state of the words of a row that lie between two other words of
+/// it. `above`, `center` and `below` hold the words of the rows from the
+/// one left of the first word of `out` to the one right of its last word,
+/// so they are two words longer than `out`.
+pub(crate) fn next_words(backend : Backend, above : &[u64], center : &[u64], below : &[u64],
+                         out : &mut [u64], transitions : u32) {
+    assert!(above.len() == out.len() + 2 && center.len() == out.len() + 2 && below.len() == out.len() + 2);
+    match backend {
+        Backend::Scalar => next_words_with::<u64>(above, center, below, out, transitions),
+        Backend::Portable => next_words_with::<Portable>(above, center, below, out, transitions),
+        // Safe as a `Grid` only switches to the backends the CPU supports
+        #[cfg(target_arch = "x86_64")]
+        Backend::Avx2 => unsafe { x86::next_words_avx2(above, center, below, out, transitions) },
+        #[cfg(target_arch = "x86_64")]
+        Backend::Avx512 => unsafe { x86::next_words_avx512(above, center, below, out, transitions) },
+        #[cfg(not(target_arch = "x86_64"))]
+        Backend::Avx2 | Backend::Avx512 => unreachable!("{} is not available on this CPU", backend),
+    }
+}
@@ -0,0 +1,313 @@
+//! Simulation controller owning the board.
+
+extern crate rand;
+extern crate rayon;
+
+use rand::Rng;
+use std::fmt;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+
+use crate::engine::{self, Engine, EngineKind, Rect};
+use crate::file_reader::{self, RleError};
+use crate::file_writer::{self, RleComments};
+use crate::grid::{Grid, Region, Topology};
+use crate::hashlife::HashLife;
+use crate::sparse::SparseGrid;
+use crate::rule::Rule;
+use crate::soup::{Soup, Symmetry};
+
+/// Settings a simulation is created with.
+pub struct Config {
+    pub board_size : (u32, u32),
+    pub rule : Rule,
+    /// How the edges of the board are glued together
+    pub topology : Topology,
+    /// RLE file loaded instead of the random soup
+    pub pattern : Option<PathBuf>,
+    /// Board cell the top left corner of the pattern is placed at
+    pub pattern_offset : (usize, usize),
+    /// Seed of the random soup, a random one is drawn if not set
+    pub seed : Option<u64>,
+    /// Fraction of the soup's area that is randomly set alive
+    pub fill_density : f64,
+    pub symmetry : Symmetry,
+    /// Area of the board the soup is placed in, the whole board if `None`
+    pub soup_region : Option<Region>,
+    /// Algorithm stepping the pattern. HashLife and the sparse grid simulate
+    /// the unbounded plane, the board size then only limits the soup and the
+    /// viewer.
+    pub engine : EngineKind,
+    /// Every step advances the pattern by `2^step_exponent` generations
+    pub step_exponent : u32,
+    /// Number of threads stepping the pattern, one per core if `None`
+    pub threads : Option<NonZeroUsize>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            board_size : (1000, 1000),
+            rule : Rule::conway(),
+            topology : Topology::Torus,
+            pattern : None,
+            pattern_offset : (0, 0),
+            seed : None,
+            fill_density : 0.5,
+            symmetry : Symmetry::C1,
+            soup_region : None,
+            engine : EngineKind::Grid,
+            step_exponent : 0,
+            threads : None,
+        }
+    }
+}
+
+/// Errors creating a simulation or loading a pattern into it.
+#[derive(Debug)]
+pub enum SimulationError {
+    Rle(RleError),
+    /// The engine can't simulate the rule
+    UnsupportedRule(String),
+    /// The step exponent exceeds the engine's `max_step_exponent()`
+    UnsupportedStepExponent(String),
+    ThreadPool(rayon::ThreadPoolBuildError),
+}
+
+impl fmt::Display for SimulationError {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SimulationError::Rle(err) => write!(f, "Error reading RLE file: {}", err),
+            SimulationError::UnsupportedRule(message) => write!(f, "{}", message),
+            SimulationError::UnsupportedStepExponent(message) => write!(f, "{}", message),
+            SimulationError::ThreadPool(err) => write!(f, "Error creating the thread pool: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for SimulationError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SimulationError::Rle(err) => Some(err),
+            SimulationError::UnsupportedRule(_) | SimulationError::UnsupportedStepExponent(_) => None,
+            SimulationError::ThreadPool(err) => Some(err),
+        }
+    }
+}
+
+impl From<RleError> for SimulationError {
+    fn from(err : RleError) -> SimulationError {
+        SimulationError::Rle(err)
+    }
+}
+
+/// Owns the engine and keeps track of the generations simulated with it.
+/// Both the viewer and the headless runner drive the simulation through it.
+pub struct Simulation {
+    engine : Box<dyn Engine>,
+    board_size : (usize, usize),
+    generation : u64,
+    seed : Option<u64>,
+    step_exponent : u32,
+    threads : Option<NonZeroUsize>,
+    /// Configured rule if the loaded pattern switched away from it
+    replaced_rule : Option<Rule>,
+    /// Pool the engine is stepped on, the global one if `None`
+    pool : Option<rayon::ThreadPool>,
+}
+
+impl Simulation {
+    /// Creates the board described by `config`, either filled with a random
+    /// soup or with the configured pattern. A pattern meant for another
+    /// rule switches to it, `replaced_rule()` then returns `config.rule`.
+    pub fn new(config : &Config) -> Result<Simulation, SimulationError> {
+        let board_size = (config.board_size.0 as usize, config.board_size.1 as usize);
+        let engine : Box<dyn Engine> = match config.engine {
+            EngineKind::Grid => Box::new(Grid::new(config.board_size, config.rule.clone(), config.topology)),
+            EngineKind::HashLife => Box::new(HashLife::new(config.rule.clone()).map_err(SimulationError::UnsupportedRule)?),
+            EngineKind::Sparse => Box::new(SparseGrid::new(config.rule.clone()).map_err(SimulationError::UnsupportedRule)?),
+        };
+        let mut sim = Simulation::from_engine(engine, board_size);
+        sim.set_step_exponent(config.step_exponent).map_err(SimulationError::UnsupportedStepExponent)?;
+        sim.set_threads(config.threads)?;
+
+        if let Some(filepath) = &config.pattern {
+            sim.replaced_rule = sim.load_pattern(filepath, config.pattern_offset)?;
+            return Ok(sim);
+        }
+
+        // Randomly initialize grid
+        let soup = Soup {
+            seed : config.seed.unwrap_or_else(|| rand::thread_rng().gen()),
+            density : config.fill_density,
+            symmetry : config.symmetry,
+            region : config.soup_region,
+        };
+        soup.fill(sim.engine.as_mut(), board_size);
+        sim.seed = Some(soup.seed);
+
+        Ok(sim)
+    }
+
+    /// Starts a simulation at generation 0 from an existing board.
+    pub fn from_grid(grid : Grid) -> Simulation {
+        let board_size = (grid.num_cols, grid.num_rows);
+        Simulation::from_engine(Box::new(grid), board_size)
+    }
+
+    /// Starts a simulation at generation 0 from an existing engine. The
+    /// board size is the area shown by the viewer.
+    pub fn from_engine(engine : Box<dyn Engine>, board_size : (usize, usize)) -> Simulation {
+        Simulation {
+            engine,
+            board_size,
+            generation : 0,
+            seed : None,
+            step_exponent : 0,
+            threads : None,
+            replaced_rule : None,
+            pool : None,
+        }
+    }
+
+    pub fn engine(&self) -> &dyn Engine {
+        self.engine.as_ref()
+    }
+
+    /// Mutable access to the engine, e.g. to edit cells between generations.
+    pub fn engine_mut(&mut self) -> &mut dyn Engine {
+        self.engine.as_mut()
+    }
+
+    pub fn board_size(&self) -> (usize, usize) {
+        self.board_size
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Seed of the random soup the board was created from, `None` if it
+    /// was created from a pattern or an existing grid.
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    pub fn rule(&self) -> Rule {
+        self.engine.rule()
+    }
+
+    /// Switches the rule, fails if the engine can't simulate it.
+    pub fn set_rule(&mut self, rule : Rule) -> Result<(), String> {
+        self.engine.set_rule(rule)
+    }
+
+    /// Rule given to `new` if the pattern it loaded switched to another
+    /// one.
+    pub fn replaced_rule(&self) -> Option<&Rule> {
+        self.replaced_rule.as_ref()
+    }
+
+    pub fn step_exponent(&self) -> u32 {
+        self.step_exponent
+    }
+
+    /// Sets the number of generations of a step to `2^step_exponent`, fails
+    /// if it exceeds the engine's `max_step_exponent()`.
+    pub fn set_step_exponent(&mut self, step_exponent : u32) -> Result<(), String> {
+        engine::check_step_exponent(step_exponent, self.engine.max_step_exponent())?;
+        self.step_exponent = step_exponent;
+        Ok(())
+    }
+
+    /// Number of threads stepping the pattern, one per core if `None`.
+    pub fn threads(&self) -> Option<NonZeroUsize> {
+        self.threads
+    }
+
+    /// Steps the pattern on `threads` threads, or on one per core if
+    /// `None`. With a single thread every step runs on the calling thread,
+    /// so profiles show the kernel without any scheduling in between.
+    pub fn set_threads(&mut self, threads : Option<NonZeroUsize>) -> Result<(), SimulationError> {
+        let single = threads.map(NonZeroUsize::get) == Some(1);
+        self.pool = match threads {
+            Some(n) if !single => Some(rayon::ThreadPoolBuilder::new()
+                                           .num_threads(n.get())
+                                           .build()
+                                           .map_err(SimulationError::ThreadPool)?),
+            _ => None,
+        };
+        self.engine.set_parallel(!single);
+        self.threads = threads;
+        Ok(())
+    }
+
+    /// Advances the pattern by `2^step_exponent` generations, fails without
+    /// changing it if the engine can't.
+    pub fn step(&mut self) -> Result<(), String> {
+        self.step_engine(self.step_exponent)
+    }
+
+    /// Advances the pattern by exactly `generations` generations: as many
+    /// steps of `2^step_exponent` generations as fit, then the rest in steps
+    /// of the powers of two it is made of from the largest down. No step is
+    /// larger than the usual one, so HashLife reuses the results it cached
+    /// for it. Stops at the first step the engine fails.
+    pub fn advance(&mut self, generations : u64) -> Result<(), String> {
+        for _ in 0..(generations >> self.step_exponent) {
+            self.step_engine(self.step_exponent)?;
+        }
+        for exponent in (0..self.step_exponent).rev().filter(|e| (generations >> e) & 1 == 1) {
+            self.step_engine(exponent)?;
+        }
+        Ok(())
+    }
+
+    fn step_engine(&mut self, exponent : u32) -> Result<(), String> {
+        let engine = &mut self.engine;
+        match &self.pool {
+            Some(pool) => pool.install(|| engine.step(exponent)),
+            None => engine.step(exponent),
+        }?;
+        self.generation += 1 << exponent;
+        Ok(())
+    }
+
+    /// Places the RLE pattern at `offset` and switches to the rule declared
+    /// in its header if it differs from the active one. Returns the rule
+    /// that was active before such a switch.
+    pub fn load_pattern<P>(&mut self,
+                           filepath : P,
+                           offset : (usize, usize)) -> Result<Option<Rule>, SimulationError>
+        where P: AsRef<Path> {
+        let p = file_reader::read_rle(filepath)?;
+
+        let mut previous = None;
+        if let Some(rule) = p.rule {
+            if rule != self.rule() {
+                previous = Some(self.rule());
+                self.set_rule(rule).map_err(SimulationError::UnsupportedRule)?;
+            }
+        }
+
+        for (v, state) in p.pattern.iter().zip(&p.states) {
+            self.engine.set_state((offset.0 + v.0) as i64, (offset.1 + v.1) as i64, *state);
+        }
+        Ok(previous)
+    }
+
+    /// Writes the alive part of the board to an RLE file. The position of
+    /// the pattern on the board and the generation are kept as comments.
+    pub fn save_rle<P>(&self, filepath : P) -> std::io::Result<()>
+        where P: AsRef<Path> {
+        let region = self.engine.bounding_box().unwrap_or(Rect { col : 0, row : 0, width : 0, height : 0 });
+        let comments = RleComments {
+            name : String::new(),
+            author : String::from("Rusty Life"),
+            comments : vec![format!("Generation {} at ({}, {}) of a {}x{} board",
+                                    self.generation, region.col, region.row,
+                                    self.board_size.0, self.board_size.1)],
+        };
+        file_writer::save_rle(self.engine.as_ref(), region, &comments, filepath)
+    }
+}
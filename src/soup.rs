@@ -0,0 +1,242 @@
+//! Seeded random soups with exact density and optional symmetry.
+
+extern crate rand_chacha;
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::engine::Engine;
+use crate::grid::Region;
+
+/// Symmetries of a soup, named as in apgsearch. The suffix gives the
+/// position of the symmetry center: `1` on a cell, `2` on the edge between
+/// two cells and `4` on the corner between four cells. `+` symmetries
+/// mirror across horizontal/vertical axes, `x` ones across diagonals.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Symmetry {
+    C1,
+    C2_1,
+    C2_2,
+    C2_4,
+    C4_1,
+    C4_4,
+    D2_P1,
+    D2_P2,
+    D2_X,
+    D4_P1,
+    D4_P2,
+    D4_P4,
+    D4_X1,
+    D4_X4,
+    D8_1,
+    D8_4,
+}
+
+const SYMMETRY_NAMES : [(Symmetry, &str); 16] = [
+    (Symmetry::C1, "C1"),
+    (Symmetry::C2_1, "C2_1"),
+    (Symmetry::C2_2, "C2_2"),
+    (Symmetry::C2_4, "C2_4"),
+    (Symmetry::C4_1, "C4_1"),
+    (Symmetry::C4_4, "C4_4"),
+    (Symmetry::D2_P1, "D2_+1"),
+    (Symmetry::D2_P2, "D2_+2"),
+    (Symmetry::D2_X, "D2_x"),
+    (Symmetry::D4_P1, "D4_+1"),
+    (Symmetry::D4_P2, "D4_+2"),
+    (Symmetry::D4_P4, "D4_+4"),
+    (Symmetry::D4_X1, "D4_x1"),
+    (Symmetry::D4_X4, "D4_x4"),
+    (Symmetry::D8_1, "D8_1"),
+    (Symmetry::D8_4, "D8_4"),
+];
+
+/// Transformation of a cell inside a `width` x `height` area.
+#[derive(Copy, Clone)]
+enum Transform {
+    Identity,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    FlipRows,
+    FlipCols,
+    Transpose,
+    AntiTranspose,
+}
+
+impl Transform {
+    fn apply(self, (x, y) : (usize, usize), width : usize, height : usize) -> (usize, usize) {
+        match self {
+            Transform::Identity => (x, y),
+            Transform::Rotate90 => (width - 1 - y, x),
+            Transform::Rotate180 => (width - 1 - x, height - 1 - y),
+            Transform::Rotate270 => (y, height - 1 - x),
+            Transform::FlipRows => (x, height - 1 - y),
+            Transform::FlipCols => (width - 1 - x, y),
+            Transform::Transpose => (y, x),
+            Transform::AntiTranspose => (width - 1 - y, height - 1 - x),
+        }
+    }
+}
+
+/// Whether a dimension of the area has to be odd, even or is free.
+#[derive(Copy, Clone)]
+enum Parity {
+    Odd,
+    Even,
+    Any,
+}
+
+fn with_parity(n : usize, parity : Parity) -> usize {
+    match parity {
+        Parity::Odd if n.is_multiple_of(2) => n.saturating_sub(1),
+        Parity::Even if !n.is_multiple_of(2) => n - 1,
+        _ => n,
+    }
+}
+
+impl Symmetry {
+    /// Transformations of the symmetry group.
+    fn group(self) -> &'static [Transform] {
+        use Transform::*;
+        match self {
+            Symmetry::C1 => &[Identity],
+            Symmetry::C2_1 | Symmetry::C2_2 | Symmetry::C2_4 => &[Identity, Rotate180],
+            Symmetry::C4_1 | Symmetry::C4_4 => &[Identity, Rotate90, Rotate180, Rotate270],
+            Symmetry::D2_P1 | Symmetry::D2_P2 => &[Identity, FlipRows],
+            Symmetry::D2_X => &[Identity, Transpose],
+            Symmetry::D4_P1 | Symmetry::D4_P2 | Symmetry::D4_P4 => &[Identity, FlipRows, FlipCols, Rotate180],
+            Symmetry::D4_X1 | Symmetry::D4_X4 => &[Identity, Transpose, AntiTranspose, Rotate180],
+            Symmetry::D8_1 | Symmetry::D8_4 => &[Identity, Rotate90, Rotate180, Rotate270,
+                                                 FlipRows, FlipCols, Transpose, AntiTranspose],
+        }
+    }
+
+    /// Required parity of width and height and whether the area is square.
+    fn shape(self) -> (Parity, Parity, bool) {
+        use Parity::*;
+        match self {
+            Symmetry::C1 => (Any, Any, false),
+            Symmetry::C2_1 | Symmetry::D4_P1 => (Odd, Odd, false),
+            Symmetry::C2_2 | Symmetry::D4_P2 => (Even, Odd, false),
+            Symmetry::C2_4 | Symmetry::D4_P4 => (Even, Even, false),
+            Symmetry::D2_P1 => (Any, Odd, false),
+            Symmetry::D2_P2 => (Any, Even, false),
+            Symmetry::D2_X => (Any, Any, true),
+            Symmetry::C4_1 | Symmetry::D4_X1 | Symmetry::D8_1 => (Odd, Odd, true),
+            Symmetry::C4_4 | Symmetry::D4_X4 | Symmetry::D8_4 => (Even, Even, true),
+        }
+    }
+
+    /// Largest area inside `region` the symmetry can be applied to, centered
+    /// on the region.
+    pub fn fit(self, region : Region) -> Region {
+        let (col_parity, row_parity, square) = self.shape();
+        let (mut width, mut height) = (region.width, region.height);
+        if square {
+            width = width.min(height);
+            height = width;
+        }
+        width = with_parity(width, col_parity);
+        height = with_parity(height, row_parity);
+        if square {
+            width = width.min(height);
+            height = width;
+        }
+        Region {
+            col : region.col + (region.width - width) / 2,
+            row : region.row + (region.height - height) / 2,
+            width,
+            height,
+        }
+    }
+}
+
+impl FromStr for Symmetry {
+    type Err = String;
+
+    fn from_str(s : &str) -> Result<Symmetry, String> {
+        SYMMETRY_NAMES.iter()
+                      .find(|(_, name)| name.eq_ignore_ascii_case(s))
+                      .map(|(sym, _)| *sym)
+                      .ok_or_else(|| format!("Unknown symmetry \"{}\"", s))
+    }
+}
+
+impl fmt::Display for Symmetry {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        let name = SYMMETRY_NAMES.iter().find(|(sym, _)| sym == self).map_or("", |(_, name)| name);
+        write!(f, "{}", name)
+    }
+}
+
+/// Description of a random soup. The same seed always produces the same
+/// soup, independent of platform and thread count.
+#[derive(Debug, Copy, Clone)]
+pub struct Soup {
+    pub seed : u64,
+    /// Fraction of the symmetry orbits in the region that are set alive
+    pub density : f64,
+    pub symmetry : Symmetry,
+    /// Area of the board the soup is placed in, the whole board if `None`
+    pub region : Option<Region>,
+}
+
+impl Soup {
+    pub fn new(seed : u64) -> Soup {
+        Soup {
+            seed,
+            density : 0.5,
+            symmetry : Symmetry::C1,
+            region : None,
+        }
+    }
+
+    /// Overwrites the soup's area of a `board_size` board simulated by
+    /// `engine` and returns the number of cells set alive.
+    ///
+    /// The cells of the area are split into orbits under the symmetry
+    /// group, then exactly `density * orbits` orbits are drawn without
+    /// replacement, so the density is exact for `C1`.
+    pub fn fill<E>(&self, engine : &mut E, board_size : (usize, usize)) -> u64
+        where E: Engine + ?Sized {
+        let board = Region { col : 0, row : 0, width : board_size.0, height : board_size.1 };
+        let area = self.symmetry.fit(self.region.unwrap_or(board));
+        let group = self.symmetry.group();
+
+        let mut representatives = Vec::new();
+        for y in 0..area.height {
+            for x in 0..area.width {
+                engine.set_cell((area.col + x) as i64, (area.row + y) as i64, false);
+                let is_min = group.iter()
+                                  .all(|t| {
+                                      let (tx, ty) = t.apply((x, y), area.width, area.height);
+                                      (ty, tx) >= (y, x)
+                                  });
+                if is_min {
+                    representatives.push((x, y));
+                }
+            }
+        }
+
+        let density = self.density.clamp(0.0, 1.0);
+        let num_alive = (representatives.len() as f64 * density).round() as usize;
+        let mut rng = ChaCha8Rng::seed_from_u64(self.seed);
+        let mut population = 0;
+        for idx in rand::seq::index::sample(&mut rng, representatives.len(), num_alive).iter() {
+            let cell = representatives[idx];
+            for t in group {
+                let (x, y) = t.apply(cell, area.width, area.height);
+                let (col, row) = ((area.col + x) as i64, (area.row + y) as i64);
+                if !engine.get_cell(col, row) {
+                    engine.set_cell(col, row, true);
+                    population += 1;
+                }
+            }
+        }
+        population
+    }
+}
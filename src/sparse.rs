@@ -0,0 +1,259 @@
+//! Unbounded plane stored as a sparse map of bit tiles.
+
+extern crate rayon;
+
+use rayon::prelude::*;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+
+use crate::engine::{self, Engine, Rect};
+use crate::kernel::{self, Transitions};
+use crate::rule::Rule;
+
+/// Number of cells along each side of a tile.
+const TILE_SIZE : i64 = 64;
+
+/// 64 rows of 64 cells, the most significant bit of a row being its
+/// leftmost cell.
+type Tile = [u64; TILE_SIZE as usize];
+
+/// Unbounded plane whose alive cells are kept in 64x64 tiles. Tiles are
+/// allocated when a pattern grows into them and freed once they are empty,
+/// so memory and time scale with the area covered by the pattern.
+pub struct SparseGrid {
+    /// Tiles by their position, tile (x, y) covers the cells from
+    /// (64x, 64y) to (64x + 63, 64y + 63)
+    tiles : HashMap<(i64, i64), Box<Tile>>,
+    rule : Rule,
+    /// Whether tiles are stepped on the threads of the rayon pool
+    parallel : bool,
+}
+
+/// Tile holding the cell and the cell's position inside of it.
+fn split(col : i64, row : i64) -> ((i64, i64), (usize, usize)) {
+    ((col.div_euclid(TILE_SIZE), row.div_euclid(TILE_SIZE)),
+     (col.rem_euclid(TILE_SIZE) as usize, row.rem_euclid(TILE_SIZE) as usize))
+}
+
+impl SparseGrid {
+    /// Creates an empty plane, fails for rules with birth on 0 neighbours
+    /// which would fill the unbounded plane and for Generations, Larger
+    /// than Life and table rules.
+    pub fn new(rule : Rule) -> Result<SparseGrid, String> {
+        Self::check_rule(&rule)?;
+        Ok(SparseGrid {
+            tiles : HashMap::new(),
+            rule,
+            parallel : true,
+        })
+    }
+
+    /// Number of allocated tiles.
+    pub fn tile_count(&self) -> usize {
+        self.tiles.len()
+    }
+
+    fn check_rule(rule : &Rule) -> Result<(), String> {
+        if rule.transitions() & 0b1 != 0 {
+            return Err(format!("The sparse grid can't simulate rule {} with birth on 0 neighbours", rule));
+        }
+        if rule.rule_table().is_some() {
+            return Err(format!("The sparse grid can't simulate the rule table {}", rule));
+        }
+        if rule.states() > 2 {
+            return Err(format!("The sparse grid can't simulate the Generations rule {}", rule));
+        }
+        if rule.larger_than_life().is_some() {
+            return Err(format!("The sparse grid can't simulate the Larger than Life rule {}", rule));
+        }
+        Ok(())
+    }
+
+    /// Positions of all allocated tiles and of the empty tiles next to
+    /// them that alive cells on a tile border may spread into.
+    fn candidates(&self) -> HashSet<(i64, i64)> {
+        let mut candidates = HashSet::with_capacity(self.tiles.len() * 2);
+        for (&(x, y), tile) in &self.tiles {
+            candidates.insert((x, y));
+            let first = tile[0];
+            let last = tile[TILE_SIZE as usize - 1];
+            let left = tile.iter().any(|r| r >> 63 != 0);
+            let right = tile.iter().any(|r| r & 1 != 0);
+            let borders = [
+                (first != 0, (x, y - 1)),
+                (last != 0, (x, y + 1)),
+                (left, (x - 1, y)),
+                (right, (x + 1, y)),
+                (first >> 63 != 0, (x - 1, y - 1)),
+                (first & 1 != 0, (x + 1, y - 1)),
+                (last >> 63 != 0, (x - 1, y + 1)),
+                (last & 1 != 0, (x + 1, y + 1)),
+            ];
+            for (alive, pos) in borders.iter() {
+                if *alive {
+                    candidates.insert(*pos);
+                }
+            }
+        }
+        candidates
+    }
+
+    /// Next generation of the tile at `pos`.
+    fn next_tile(&self, (x, y) : (i64, i64), transitions : &Transitions) -> Tile {
+        // Tiles around pos, indexed by [dy + 1][dx + 1]
+        let mut around : [[Option<&Tile>; 3]; 3] = [[None; 3]; 3];
+        for (dy, tiles) in around.iter_mut().enumerate() {
+            for (dx, tile) in tiles.iter_mut().enumerate() {
+                *tile = self.tiles.get(&(x + dx as i64 - 1, y + dy as i64 - 1)).map(|t| t.as_ref());
+            }
+        }
+        // Row `row` of the tile at pos and of its left and right neighbours,
+        // rows -1 and 64 belong to the tiles above and below
+        let words = |row : i64| -> [u64; 3] {
+            let (dy, r) = match row {
+                -1 => (0, TILE_SIZE as usize - 1),
+                TILE_SIZE => (2, 0),
+                r => (1, r as usize),
+            };
+            let word = |dx : usize| around[dy][dx].map_or(0, |t| t[r]);
+            [word(0), word(1), word(2)]
+        };
+
+        let mut next = [0_u64; TILE_SIZE as usize];
+        for (r, out) in next.iter_mut().enumerate() {
+            let r = r as i64;
+            *out = kernel::next_word(words(r - 1), words(r), words(r + 1), transitions);
+        }
+        next
+    }
+
+    /// Advances the plane by one generation.
+    fn step_once(&mut self) {
+        let transitions = Transitions::of(&self.rule);
+        let next = |pos| Some((pos, self.next_tile(pos, &transitions)))
+                             .filter(|(_, tile)| tile.iter().any(|r| *r != 0))
+                             .map(|(pos, tile)| (pos, Box::new(tile)));
+        let candidates = self.candidates();
+        self.tiles = if self.parallel {
+            candidates.into_par_iter().filter_map(next).collect::<Vec<_>>().into_iter().collect()
+        } else {
+            candidates.into_iter().filter_map(next).collect()
+        };
+    }
+}
+
+impl Engine for SparseGrid {
+    fn rule(&self) -> Rule {
+        self.rule.clone()
+    }
+
+    fn set_rule(&mut self, rule : Rule) -> Result<(), String> {
+        Self::check_rule(&rule)?;
+        self.rule = rule;
+        Ok(())
+    }
+
+    fn get_cell(&self, col : i64, row : i64) -> bool {
+        let (pos, (c, r)) = split(col, row);
+        self.tiles.get(&pos).is_some_and(|t| (t[r] >> (63 - c)) & 1 == 1)
+    }
+
+    fn set_cell(&mut self, col : i64, row : i64, value : bool) {
+        let (pos, (c, r)) = split(col, row);
+        let mask = 1_u64 << (63 - c);
+        if value {
+            self.tiles.entry(pos).or_insert_with(|| Box::new([0; TILE_SIZE as usize]))[r] |= mask;
+        } else if let Some(tile) = self.tiles.get_mut(&pos) {
+            tile[r] &= !mask;
+            if tile.iter().all(|r| *r == 0) {
+                self.tiles.remove(&pos);
+            }
+        }
+    }
+
+    fn population(&self) -> u64 {
+        self.tiles.values()
+                  .map(|t| t.iter().map(|r| r.count_ones() as u64).sum::<u64>())
+                  .sum()
+    }
+
+    fn bounding_box(&self) -> Option<Rect> {
+        let mut min = (i64::MAX, i64::MAX);
+        let mut max = (i64::MIN, i64::MIN);
+        for (&(x, y), tile) in &self.tiles {
+            let cols = tile.iter().fold(0, |acc, r| acc | r);
+            let first_row = tile.iter().position(|r| *r != 0).unwrap_or(0) as i64;
+            let last_row = tile.iter().rposition(|r| *r != 0).unwrap_or(0) as i64;
+            min.0 = min.0.min(x * TILE_SIZE + cols.leading_zeros() as i64);
+            max.0 = max.0.max(x * TILE_SIZE + 63 - cols.trailing_zeros() as i64);
+            min.1 = min.1.min(y * TILE_SIZE + first_row);
+            max.1 = max.1.max(y * TILE_SIZE + last_row);
+        }
+        if self.tiles.is_empty() {
+            return None;
+        }
+        Some(Rect {
+            col : min.0,
+            row : min.1,
+            width : max.0 - min.0 + 1,
+            height : max.1 - min.1 + 1,
+        })
+    }
+
+    fn step(&mut self, exponent : u32) -> Result<(), String> {
+        engine::check_step_exponent(exponent, Engine::max_step_exponent(self))?;
+        for _ in 0..(1_u64 << exponent) {
+            self.step_once();
+        }
+        Ok(())
+    }
+
+    fn max_step_exponent(&self) -> u32 {
+        engine::MAX_ITERATED_STEP_EXPONENT
+    }
+
+    fn state_hash(&self) -> u64 {
+        // Summing the hashes of the tiles makes it independent of their order
+        self.tiles.iter()
+                  .map(|(pos, tile)| {
+                      let mut hasher = DefaultHasher::new();
+                      pos.hash(&mut hasher);
+                      tile.hash(&mut hasher);
+                      hasher.finish()
+                  })
+                  .fold(0, u64::wrapping_add)
+    }
+
+    fn fill_words(&self, area : Rect, words : &mut [u16]) {
+        words.iter_mut().for_each(|w| *w = 0);
+        let words_per_row = (area.width as usize).div_ceil(16);
+        let (first, _) = split(area.col, area.row);
+        let (last, _) = split(area.col + area.width - 1, area.row + area.height - 1);
+
+        for (&(x, y), tile) in &self.tiles {
+            if x < first.0 || x > last.0 || y < first.1 || y > last.1 {
+                continue;
+            }
+            for (r, &bits) in tile.iter().enumerate().filter(|(_, b)| **b != 0) {
+                let row = y * TILE_SIZE + r as i64 - area.row;
+                if row < 0 || row >= area.height {
+                    continue;
+                }
+                let mut rest = bits;
+                while rest != 0 {
+                    let c = rest.leading_zeros() as i64;
+                    rest &= !(1 << (63 - c));
+                    let col = x * TILE_SIZE + c - area.col;
+                    if col >= 0 && col < area.width {
+                        words[row as usize * words_per_row + col as usize / 16] |= 0x8000 >> (col % 16);
+                    }
+                }
+            }
+        }
+    }
+
+    fn set_parallel(&mut self, parallel : bool) {
+        self.parallel = parallel;
+    }
+}
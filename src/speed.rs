@@ -0,0 +1,144 @@
+//! How fast an interactive simulation advances.
+//!
+//! Like in Golly a step advances `2^step_exponent` generations; the speed
+//! decides how many steps are taken on each frame.
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// Rate at which steps are taken.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Speed {
+    /// One step whenever the delay has passed since the previous one
+    Delay(Duration),
+    /// A fixed number of steps on every frame
+    StepsPerFrame(u64),
+    /// As many steps as needed to keep up this many generations per
+    /// second, independent of the frame rate
+    GensPerSecond(f64),
+}
+
+impl Speed {
+    /// Speeds up by `factor`: shortens the delay by `factor` ms, or
+    /// multiplies the steps per frame or generations per second with it.
+    pub fn faster(self, factor : u32) -> Speed {
+        match self {
+            Speed::Delay(delay) => Speed::Delay(delay.saturating_sub(Duration::from_millis(factor as u64))),
+            Speed::StepsPerFrame(steps) => Speed::StepsPerFrame(steps.saturating_mul(factor as u64)),
+            Speed::GensPerSecond(gens) => Speed::GensPerSecond(gens * factor as f64),
+        }
+    }
+
+    /// Slows down by `factor`, the inverse of `faster`. Never gets slower
+    /// than one step or one generation per second on every frame.
+    pub fn slower(self, factor : u32) -> Speed {
+        match self {
+            Speed::Delay(delay) => Speed::Delay(delay + Duration::from_millis(factor as u64)),
+            Speed::StepsPerFrame(steps) => Speed::StepsPerFrame((steps / factor as u64).max(1)),
+            Speed::GensPerSecond(gens) => Speed::GensPerSecond((gens / factor as f64).max(1.0)),
+        }
+    }
+}
+
+impl Default for Speed {
+    fn default() -> Speed {
+        Speed::Delay(Duration::from_millis(10))
+    }
+}
+
+impl FromStr for Speed {
+    type Err = String;
+
+    /// Parses "10ms" as a delay, "4/frame" as steps per frame and "1000/s"
+    /// as generations per second.
+    fn from_str(s : &str) -> Result<Speed, String> {
+        let invalid = || format!("expected a delay like \"10ms\", steps like \"4/frame\" \
+                                  or generations like \"1000/s\", found \"{}\"", s);
+        let s = s.trim();
+        if let Some(ms) = s.strip_suffix("ms") {
+            ms.trim().parse().map(|ms| Speed::Delay(Duration::from_millis(ms))).map_err(|_| invalid())
+        } else if let Some(steps) = s.strip_suffix("/frame") {
+            match steps.trim().parse() {
+                Ok(steps) if steps > 0 => Ok(Speed::StepsPerFrame(steps)),
+                _ => Err(invalid()),
+            }
+        } else if let Some(gens) = s.strip_suffix("/s") {
+            match gens.trim().parse::<f64>() {
+                Ok(gens) if gens >= 1.0 && gens.is_finite() => Ok(Speed::GensPerSecond(gens)),
+                _ => Err(invalid()),
+            }
+        } else {
+            Err(invalid())
+        }
+    }
+}
+
+impl fmt::Display for Speed {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Speed::Delay(delay) => write!(f, "{}ms", delay.as_millis()),
+            Speed::StepsPerFrame(steps) => write!(f, "{}/frame", steps),
+            Speed::GensPerSecond(gens) => write!(f, "{}/s", gens),
+        }
+    }
+}
+
+/// Decides on every frame how many steps are due to keep up the `Speed`.
+pub struct Pacer {
+    speed : Speed,
+    /// When steps were last handed out
+    last : Instant,
+    /// Generations owed at `Speed::GensPerSecond` that didn't make up a
+    /// whole step yet
+    owed : f64,
+}
+
+impl Pacer {
+    pub fn new(speed : Speed, now : Instant) -> Pacer {
+        Pacer { speed, last : now, owed : 0.0 }
+    }
+
+    pub fn speed(&self) -> Speed {
+        self.speed
+    }
+
+    pub fn set_speed(&mut self, speed : Speed) {
+        self.speed = speed;
+        self.owed = 0.0;
+    }
+
+    /// Starts counting from `now`, e.g. when a paused simulation resumes,
+    /// so the pause isn't made up for.
+    pub fn reset(&mut self, now : Instant) {
+        self.last = now;
+        self.owed = 0.0;
+    }
+
+    /// Number of steps of `step_size` generations to take on the frame at
+    /// `now`. At most a second worth of generations is handed out at once,
+    /// so a simulation that can't keep up doesn't pile up a backlog.
+    pub fn steps_due(&mut self, now : Instant, step_size : u64) -> u64 {
+        let elapsed = now.saturating_duration_since(self.last);
+        match self.speed {
+            Speed::Delay(delay) => {
+                if elapsed < delay {
+                    return 0;
+                }
+                self.last = now;
+                1
+            }
+            Speed::StepsPerFrame(steps) => {
+                self.last = now;
+                steps
+            }
+            Speed::GensPerSecond(gens) => {
+                self.last = now;
+                self.owed = (self.owed + gens * elapsed.as_secs_f64()).min(gens.max(step_size as f64));
+                let steps = (self.owed / step_size as f64) as u64;
+                self.owed -= (steps * step_size) as f64;
+                steps
+            }
+        }
+    }
+}
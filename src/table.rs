@@ -0,0 +1,476 @@
+//! Multi-state rules given as tables of transitions in Golly's `.rule`
+//! format, such as WireWorld. A cell is in one of up to 256 states and
+//! changes to the output of the first transition that matches it and its
+//! neighbours, or keeps its state if none does.
+//!
+//! Variables and symmetries are expanded when the table is read. The
+//! transitions are then compiled into one bit set per position and state:
+//! bit `t` of the set of position `p` and state `s` is set if transition
+//! `t` accepts state `s` at `p`. Intersecting the sets of a cell and its
+//! neighbours matches it against 64 transitions per word.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use crate::rule::{Neighbourhood, MAX_STATES};
+
+/// Rule tables that can be used by name without a `.rule` file.
+const BUILTIN : [&str; 1] = [include_str!("../rules/WireWorld.rule")];
+
+/// The built-in rule tables, parsed on first use.
+fn builtin() -> &'static [RuleTable] {
+    static TABLES : OnceLock<Vec<RuleTable>> = OnceLock::new();
+    TABLES.get_or_init(|| {
+        BUILTIN.iter().map(|text| text.parse().expect("Built-in rule tables are valid")).collect()
+    })
+}
+
+/// Largest number of transitions after expanding variables and symmetries.
+const MAX_TRANSITIONS : usize = 1 << 20;
+
+/// Neighbours of the Moore neighbourhood in the order of a transition,
+/// clockwise from the cell above as (column, row) offsets.
+const MOORE : [(isize, isize); 8] = [(0, -1), (1, -1), (1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1)];
+
+const VON_NEUMANN : [(isize, isize); 4] = [(0, -1), (1, 0), (0, 1), (-1, 0)];
+
+/// The six neighbours of a hexagonal grid sheared onto the square one,
+/// see `Neighbourhood::Hexagonal`.
+const HEXAGONAL : [(isize, isize); 6] = [(0, -1), (1, 0), (1, 1), (0, 1), (-1, 0), (-1, -1)];
+
+/// Set of states, bit `s % 64` of word `s / 64` standing for state `s`.
+type States = [u64; 4];
+
+fn single(state : u8) -> States {
+    let mut set = [0; 4];
+    set[state as usize / 64] |= 1 << (state % 64);
+    set
+}
+
+fn contains(set : &States, state : usize) -> bool {
+    (set[state / 64] >> (state % 64)) & 1 == 1
+}
+
+/// Rearranges `v` into the next larger permutation, `false` once it is the
+/// largest one. Equal entries are never swapped, so starting from sorted
+/// entries every distinct arrangement is visited once.
+fn next_permutation(v : &mut [States]) -> bool {
+    let Some(i) = (1..v.len()).rev().find(|i| v[i - 1] < v[*i]) else {
+        return false;
+    };
+    let j = (i..v.len()).rev().find(|j| v[*j] > v[i - 1]).unwrap();
+    v.swap(i - 1, j);
+    v[i..].reverse();
+    true
+}
+
+/// Rearrangements of the neighbours a transition also applies to.
+enum Symmetries {
+    /// Neighbour `i` of a rearrangement is neighbour `p[i]` of the
+    /// transition, for every `p`
+    Permutations(Vec<Vec<usize>>),
+    /// Any order of the neighbours
+    Permute,
+}
+
+impl Symmetries {
+    /// The symmetries called `name` in a table on `neighbourhood`.
+    fn new(name : &str, neighbourhood : Neighbourhood) -> Result<Symmetries, String> {
+        let n = offsets(neighbourhood).len();
+        let rotations = |k : usize| (0..k).map(|r| (0..n).map(|i| (i + r * n / k) % n).collect())
+                                          .collect::<Vec<Vec<usize>>>();
+        let reflected = |rotations : Vec<Vec<usize>>| {
+            let mirrored = rotations.iter().map(|p| (0..n).map(|i| p[(n - i) % n]).collect()).collect::<Vec<_>>();
+            rotations.into_iter().chain(mirrored).collect()
+        };
+        let (rotate, rotate_reflect) : (&[usize], &[usize]) = match neighbourhood {
+            Neighbourhood::Moore => (&[4, 8], &[4, 8]),
+            Neighbourhood::VonNeumann => (&[4], &[4]),
+            Neighbourhood::Hexagonal => (&[2, 3, 6], &[6]),
+            Neighbourhood::Circular => (&[], &[]),
+        };
+        let k = |prefix : &str, suffix : &str| name.strip_prefix(prefix)
+                                                   .and_then(|k| k.strip_suffix(suffix))
+                                                   .and_then(|k| k.parse::<usize>().ok());
+        match name {
+            "none" => Ok(Symmetries::Permutations(rotations(1))),
+            "permute" => Ok(Symmetries::Permute),
+            "reflect_horizontal" => Ok(Symmetries::Permutations(reflected(rotations(1)))),
+            _ => match (k("rotate", "reflect"), k("rotate", "")) {
+                (Some(k), _) if rotate_reflect.contains(&k) => Ok(Symmetries::Permutations(reflected(rotations(k)))),
+                (_, Some(k)) if rotate.contains(&k) => Ok(Symmetries::Permutations(rotations(k))),
+                _ => Err(format!("unknown symmetries \"{}\" for the {} neighborhood", name, neighbourhood_name(neighbourhood))),
+            },
+        }
+    }
+}
+
+/// Neighbours of a cell in the order of a transition.
+fn offsets(neighbourhood : Neighbourhood) -> &'static [(isize, isize)] {
+    match neighbourhood {
+        Neighbourhood::VonNeumann => &VON_NEUMANN,
+        Neighbourhood::Hexagonal => &HEXAGONAL,
+        Neighbourhood::Moore | Neighbourhood::Circular => &MOORE,
+    }
+}
+
+fn neighbourhood_name(neighbourhood : Neighbourhood) -> &'static str {
+    match neighbourhood {
+        Neighbourhood::VonNeumann => "vonNeumann",
+        Neighbourhood::Hexagonal => "hexagonal",
+        Neighbourhood::Moore | Neighbourhood::Circular => "Moore",
+    }
+}
+
+/// State of the @TABLE section while it is read line by line.
+#[derive(Default)]
+struct TableParser {
+    states : Option<u16>,
+    neighbourhood : Option<Neighbourhood>,
+    symmetries : Option<Symmetries>,
+    variables : HashMap<String, States>,
+    /// Input sets of the centre cell and the neighbours, and the output of
+    /// every transition in the order they are tried
+    transitions : Vec<(Vec<States>, u8)>,
+    /// The inputs in `transitions`, a later transition with the same
+    /// inputs could never match
+    seen : HashSet<Vec<States>>,
+}
+
+impl TableParser {
+    fn states(&self) -> Result<u16, String> {
+        self.states.ok_or_else(|| "expected \"n_states\" before the variables and transitions".to_string())
+    }
+
+    fn neighbourhood(&self) -> Result<Neighbourhood, String> {
+        self.neighbourhood.ok_or_else(|| "expected \"neighborhood\" before the variables and transitions".to_string())
+    }
+
+    /// The states a variable or a number stands for.
+    fn value(&self, entry : &str) -> Result<States, String> {
+        if let Some(set) = self.variables.get(entry) {
+            return Ok(*set);
+        }
+        match entry.parse::<u16>() {
+            Ok(state) if state < self.states()? => Ok(single(state as u8)),
+            Ok(state) => Err(format!("state {} isn't below n_states {}", state, self.states()?)),
+            Err(_) => Err(format!("unknown variable \"{}\"", entry)),
+        }
+    }
+
+    fn parse_line(&mut self, l : &str) -> Result<(), String> {
+        if let Some(var) = l.strip_prefix("var ") {
+            let (name, values) = var.split_once('=').ok_or_else(|| format!("expected \"var name={{...}}\", found \"{}\"", l))?;
+            let values = values.trim();
+            let values = values.strip_prefix('{')
+                               .and_then(|v| v.strip_suffix('}'))
+                               .ok_or_else(|| format!("expected the values of \"{}\" in braces", name.trim()))?;
+            let mut set = [0; 4];
+            for value in values.split(',') {
+                for (word, v) in set.iter_mut().zip(self.value(value.trim())?) {
+                    *word |= v;
+                }
+            }
+            self.variables.insert(name.trim().to_string(), set);
+            return Ok(());
+        }
+        if let Some((key, value)) = l.split_once([':', '=']) {
+            let value = value.trim();
+            match key.trim() {
+                "n_states" | "num_states" => match value.parse::<u16>() {
+                    Ok(n) if (2..=MAX_STATES).contains(&n) => self.states = Some(n),
+                    _ => return Err(format!("expected 2 to {} states, found \"{}\"", MAX_STATES, value)),
+                },
+                "neighborhood" | "neighbourhood" => self.neighbourhood = Some(match value {
+                    "Moore" => Neighbourhood::Moore,
+                    "vonNeumann" => Neighbourhood::VonNeumann,
+                    "hexagonal" => Neighbourhood::Hexagonal,
+                    _ => return Err(format!("unsupported neighborhood \"{}\", expected Moore, vonNeumann or hexagonal", value)),
+                }),
+                "symmetries" => self.symmetries = Some(Symmetries::new(value, self.neighbourhood()?)?),
+                key => return Err(format!("unknown setting \"{}\"", key)),
+            }
+            return Ok(());
+        }
+        self.parse_transition(l)
+    }
+
+    /// Adds a transition "C,N,NE,E,SE,S,SW,W,NW,C'" for the Moore
+    /// neighbourhood, "C,N,E,S,W,C'" for von Neumann or
+    /// "C,N,E,SE,S,W,NW,C'" for the hexagonal one. The commas may be left
+    /// out if every entry is a single digit. A variable used more than once
+    /// takes the same value everywhere.
+    fn parse_transition(&mut self, l : &str) -> Result<(), String> {
+        let entries = if l.contains(',') {
+            l.split(',').map(str::trim).collect::<Vec<_>>()
+        } else {
+            l.char_indices().map(|(i, c)| &l[i..i + c.len_utf8()]).collect()
+        };
+        let n = offsets(self.neighbourhood()?).len();
+        if entries.len() != n + 2 {
+            return Err(format!("expected {} entries in a transition, found {}", n + 2, entries.len()));
+        }
+        if self.symmetries.is_none() {
+            self.symmetries = Some(Symmetries::new("none", self.neighbourhood()?)?);
+        }
+
+        // Variables used more than once are bound, every combination of
+        // their values is a transition of its own
+        let mut bound = Vec::<&str>::new();
+        for entry in entries.iter().filter(|e| self.variables.contains_key(**e)) {
+            if entries.iter().filter(|e| *e == entry).count() > 1 && !bound.contains(entry) {
+                bound.push(*entry);
+            }
+        }
+        let output = entries[n + 1];
+        if !bound.contains(&output) {
+            let output = self.value(output)?;
+            if output.iter().map(|w| w.count_ones()).sum::<u32>() != 1 {
+                return Err(format!("the output \"{}\" has to be a state or a variable of the inputs", entries[n + 1]));
+            }
+        }
+        let states = self.states()? as usize;
+        let values = bound.iter()
+                          .map(|name| (0..states).filter(|s| contains(&self.variables[*name], *s))
+                                                                  .map(|s| s as u8)
+                                                                  .collect::<Vec<_>>())
+                          .collect::<Vec<_>>();
+        let mut choice = vec![0; bound.len()];
+        loop {
+            let value = |entry : &str| -> Result<States, String> {
+                match bound.iter().position(|name| *name == entry) {
+                    Some(i) => Ok(single(values[i][choice[i]])),
+                    None => self.value(entry),
+                }
+            };
+            let inputs = entries[..=n].iter().map(|e| value(e)).collect::<Result<Vec<_>, _>>()?;
+            let output = value(output)?;
+            let output = (0..256).find(|s| contains(&output, *s)).unwrap() as u8;
+            self.add_symmetric(inputs, output)?;
+
+            // Next combination of the bound values
+            let Some(i) = (0..bound.len()).find(|i| choice[*i] + 1 < values[*i].len()) else {
+                break;
+            };
+            choice[i] += 1;
+            choice[..i].iter_mut().for_each(|c| *c = 0);
+        }
+        Ok(())
+    }
+
+    /// Adds the transition and its rearrangements under the symmetries.
+    fn add_symmetric(&mut self, inputs : Vec<States>, output : u8) -> Result<(), String> {
+        let arranged = |neighbours : &mut dyn Iterator<Item = States>| std::iter::once(inputs[0]).chain(neighbours).collect::<Vec<_>>();
+        let arrangements = match self.symmetries.as_ref().unwrap() {
+            Symmetries::Permutations(permutations) => {
+                permutations.iter().map(|p| arranged(&mut p.iter().map(|i| inputs[1 + i]))).collect::<Vec<_>>()
+            }
+            Symmetries::Permute => {
+                let mut neighbours = inputs[1..].to_vec();
+                neighbours.sort_unstable();
+                let mut arrangements = vec![arranged(&mut neighbours.iter().copied())];
+                while next_permutation(&mut neighbours) {
+                    arrangements.push(arranged(&mut neighbours.iter().copied()));
+                }
+                arrangements
+            }
+        };
+        for inputs in arrangements {
+            if self.seen.insert(inputs.clone()) {
+                if self.transitions.len() == MAX_TRANSITIONS {
+                    return Err(format!("more than {} transitions after expanding variables and symmetries", MAX_TRANSITIONS));
+                }
+                self.transitions.push((inputs, output));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A rule given by a table of transitions, read from the @TABLE section
+/// of a `.rule` file, with the colours of its states from the @COLORS
+/// section.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleTable {
+    name : String,
+    states : u16,
+    neighbourhood : Neighbourhood,
+    /// Words of the set of transitions of one position and state
+    words : usize,
+    /// Transitions accepting each state at each position, the cell itself
+    /// first and then its neighbours in the order of a transition
+    masks : Vec<u64>,
+    /// Next state of the cell for every transition
+    outputs : Vec<u8>,
+    colors : Vec<Option<[u8; 3]>>,
+}
+
+impl RuleTable {
+    /// Reads the `.rule` file at `filepath`.
+    pub fn read<P>(filepath : P) -> Result<RuleTable, String>
+        where P: AsRef<Path> {
+        let filepath = filepath.as_ref();
+        let text = fs::read_to_string(filepath).map_err(|err| format!("Can't read \"{}\": {}", filepath.display(), err))?;
+        text.parse().map_err(|err| format!("\"{}\", {}", filepath.display(), err))
+    }
+
+    /// The built-in rule table called `name`, ignoring case, or else the
+    /// one in the file `<name>.rule` of the current directory. Names only
+    /// consist of letters, digits, '-' and '_', so they never lead to a
+    /// file elsewhere.
+    pub fn find(name : &str) -> Result<RuleTable, String> {
+        if let Some(table) = builtin().iter().find(|table| table.name.eq_ignore_ascii_case(name)) {
+            return Ok(table.clone());
+        }
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return Err(format!("\"{}\" is not the name of a rule table", name));
+        }
+        let filepath = format!("{}.rule", name);
+        if !Path::new(&filepath).exists() {
+            return Err(format!("No built-in rule table \"{}\" and no file \"{}\"", name, filepath));
+        }
+        RuleTable::read(filepath)
+    }
+
+    /// Name given after @RULE.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn states(&self) -> u16 {
+        self.states
+    }
+
+    pub fn neighbourhood(&self) -> Neighbourhood {
+        self.neighbourhood
+    }
+
+    /// Neighbours of a cell as (column, row) offsets, in the order
+    /// `next_state` takes them.
+    pub fn offsets(&self) -> &'static [(isize, isize)] {
+        offsets(self.neighbourhood)
+    }
+
+    /// Colour of a state from the @COLORS section, `None` if it isn't given.
+    pub fn color(&self, state : u8) -> Option<[u8; 3]> {
+        self.colors.get(state as usize).copied().flatten()
+    }
+
+    /// Next state of a cell in state `cell` whose neighbours, in the order
+    /// of `offsets`, are in the states `neighbours`.
+    #[inline]
+    pub fn next_state(&self, cell : u8, neighbours : &[u8]) -> u8 {
+        let states = self.states as usize;
+        for word in 0..self.words {
+            let mut matches = self.masks[cell as usize * self.words + word];
+            for (position, state) in neighbours.iter().enumerate() {
+                if matches == 0 {
+                    break;
+                }
+                matches &= self.masks[((position + 1) * states + *state as usize) * self.words + word];
+            }
+            if matches != 0 {
+                return self.outputs[word * 64 + matches.trailing_zeros() as usize];
+            }
+        }
+        cell
+    }
+}
+
+/// Reads a line of the @COLORS section, either "state r g b" or a
+/// gradient "r1 g1 b1 r2 g2 b2" from state 1 to the last one. Anything
+/// after the numbers is a comment.
+fn parse_colors(l : &str, colors : &mut [Option<[u8; 3]>]) -> Result<(), String> {
+    let numbers = l.split_whitespace().map_while(|w| w.parse::<u8>().ok()).collect::<Vec<_>>();
+    match *numbers.as_slice() {
+        [state, r, g, b] => match colors.get_mut(state as usize) {
+            Some(color) => *color = Some([r, g, b]),
+            None => return Err(format!("state {} isn't below n_states {}", state, colors.len())),
+        },
+        [r1, g1, b1, r2, g2, b2] => {
+            let last = colors.len() - 1;
+            for (state, color) in colors.iter_mut().enumerate().skip(1) {
+                let t = if last > 1 { (state - 1) as f32 / (last - 1) as f32 } else { 0.0 };
+                let mix = |a : u8, b : u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+                *color = Some([mix(r1, r2), mix(g1, g2), mix(b1, b2)]);
+            }
+        }
+        _ => return Err(format!("expected \"state r g b\" or \"r1 g1 b1 r2 g2 b2\", found \"{}\"", l)),
+    }
+    Ok(())
+}
+
+impl FromStr for RuleTable {
+    type Err = String;
+
+    /// Parses the contents of a `.rule` file starting with "@RULE name".
+    /// Only the @TABLE and @COLORS sections are read, others such as
+    /// @ICONS are skipped.
+    fn from_str(s : &str) -> Result<RuleTable, String> {
+        let mut name = None;
+        let mut section = "";
+        let mut has_table = false;
+        let mut table = TableParser::default();
+        let mut color_lines = Vec::new();
+        for (line, l) in s.lines().enumerate() {
+            let at = |message : String| format!("line {}: {}", line + 1, message);
+            let l = l.split('#').next().unwrap_or("").trim();
+            if l.is_empty() {
+                continue;
+            }
+            if let Some(header) = l.strip_prefix('@') {
+                let mut words = header.split_whitespace();
+                section = words.next().unwrap_or("");
+                match section {
+                    "RULE" if name.is_none() => {
+                        name = Some(words.next().ok_or_else(|| at("expected a name after @RULE".to_string()))?);
+                    }
+                    "TABLE" => has_table = true,
+                    "TREE" => return Err(at("@TREE isn't supported, only @TABLE".to_string())),
+                    _ => (),
+                }
+                continue;
+            }
+            match section {
+                "" => return Err(at("expected \"@RULE name\" first".to_string())),
+                "TABLE" => table.parse_line(l).map_err(at)?,
+                "COLORS" => color_lines.push((line, l)),
+                _ => (),
+            }
+        }
+        let name = name.ok_or_else(|| "missing \"@RULE name\"".to_string())?;
+        if !has_table {
+            return Err("missing @TABLE section".to_string());
+        }
+        let states = table.states()?;
+        let neighbourhood = table.neighbourhood()?;
+
+        let mut colors = vec![None; states as usize];
+        for (line, l) in color_lines {
+            parse_colors(l, &mut colors).map_err(|err| format!("line {}: {}", line + 1, err))?;
+        }
+
+        let words = table.transitions.len().div_ceil(64);
+        let mut masks = vec![0_u64; (offsets(neighbourhood).len() + 1) * states as usize * words];
+        for (t, (inputs, _)) in table.transitions.iter().enumerate() {
+            for (position, set) in inputs.iter().enumerate() {
+                for state in (0..states as usize).filter(|s| contains(set, *s)) {
+                    masks[(position * states as usize + state) * words + t / 64] |= 1 << (t % 64);
+                }
+            }
+        }
+
+        Ok(RuleTable {
+            name : name.to_string(),
+            states,
+            neighbourhood,
+            words,
+            masks,
+            outputs : table.transitions.iter().map(|(_, output)| *output).collect(),
+            colors,
+        })
+    }
+}
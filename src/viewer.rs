@@ -0,0 +1,283 @@
+extern crate sdl2;
+
+use std::io::{Write, stdout};
+use std::time::Instant;
+
+use rusty_life::rule;
+use rusty_life::speed::Speed;
+use rusty_life::worker::{Command, Snapshot, Worker};
+use rusty_life::Simulation;
+
+mod render;
+mod input;
+mod view;
+
+macro_rules! enum_str {
+    (enum $name:ident {
+        $($variant:ident),*,
+    }) => {
+        #[allow(clippy::upper_case_acronyms)]
+        enum $name {
+            $($variant),*
+        }
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                match self {
+                    $($name::$variant => write!(f, stringify!($variant))),*
+                }
+            }
+        }
+    };
+}
+
+enum_str!{
+enum SimStatus {
+    PAUSED,
+    RUNNING,
+}
+}
+
+struct Statistics {
+    fps : u64,
+    rendering : bool,
+    board_width : u128,
+    board_height : u128,
+    resolution_width : u32,
+    resolution_height : u32,
+}
+
+impl Statistics {
+    fn new() -> Statistics {
+        Statistics {
+            fps : 0,
+            rendering : true,
+            board_width : 0,
+            board_height : 0,
+            resolution_width : 0,
+            resolution_height : 0,
+        }
+    }
+}
+
+pub struct RustyLife {
+    renderer : render::Renderer,
+    /// Thread stepping the simulation, the viewer only draws its snapshots
+    worker : Worker,
+    input : input::Input,
+    view : view::OrthoView,
+    stats : Statistics,
+    /// Generations the G key advances the simulation by
+    step_by : u64,
+    /// Rule the palette and the cell layout of the renderer are set up for
+    rule : rule::Rule,
+}
+
+impl RustyLife {
+
+    pub fn new (name : &str,
+                window_size : (u32, u32),
+                paused : bool,
+                speed : Speed,
+                step_by : u64,
+                sim : Simulation) -> RustyLife {
+        let mut stats = Statistics::new();
+        let (num_cols, num_rows) = sim.board_size();
+        stats.board_width = num_cols as u128;
+        stats.board_height = num_rows as u128;
+        stats.resolution_width = window_size.0;
+        stats.resolution_height = window_size.1;
+
+        let renderer = render::Renderer::new(name,
+            window_size,
+            num_rows as u32,
+            num_cols as u32);
+
+        let input = renderer.create_input();
+        let view = view::OrthoView::new(window_size);
+
+        Self{renderer,
+             input,
+             worker : Worker::spawn(sim, speed, !paused),
+             view,
+             stats,
+             step_by,
+             rule : rule::Rule::conway(),
+            }
+    }
+
+    fn cycle_rule(&mut self) {
+        let current = &self.worker.snapshot().rule;
+        let next = rule::PRESETS.iter()
+                                .position(|(_, r)| r.parse().as_ref() == Ok(current))
+                                .map_or(0, |idx| (idx + 1) % rule::PRESETS.len());
+        match rule::PRESETS[next].1.parse() {
+            Ok(rule) => self.worker.send(Command::SetRule(rule)),
+            Err(err) => eprintln!("Error switching rule: \n\t{}", err),
+        }
+    }
+
+    /// Numpad + raises the number describing the speed, i.e. the delay
+    /// between steps, the steps per frame or the generations per second,
+    /// Numpad - lowers it. With SHIFT it changes ten times as much.
+    fn change_speed(&mut self, raise : bool, shift : bool) {
+        let speed = self.worker.snapshot().speed;
+        let speed = match (speed, raise) {
+            (Speed::Delay(_), true) => speed.slower(if shift { 10 } else { 1 }),
+            (Speed::Delay(_), false) => speed.faster(if shift { 10 } else { 1 }),
+            (_, true) => speed.faster(if shift { 10 } else { 2 }),
+            (_, false) => speed.slower(if shift { 10 } else { 2 }),
+        };
+        self.worker.send(Command::SetSpeed(speed));
+    }
+
+    /// Switches between a delay per step, steps per frame and generations
+    /// per second.
+    fn cycle_speed_mode(&mut self) {
+        let speed = match self.worker.snapshot().speed {
+            Speed::Delay(_) => Speed::StepsPerFrame(1),
+            Speed::StepsPerFrame(_) => Speed::GensPerSecond(1000.0),
+            Speed::GensPerSecond(_) => Speed::default(),
+        };
+        self.worker.send(Command::SetSpeed(speed));
+    }
+
+    pub fn run(&mut self) {
+        let _ = crossterm::execute!(stdout(), crossterm::cursor::SavePosition);
+
+        let mut frame_counter_timer = Instant::now();
+        let mut frame_timer = Instant::now();
+        let mut fps_counter = 0;
+        let mut run = true;
+
+        while run {
+            self.input.update_input();
+            let input_map = self.input.get_input_map();
+
+            if input_map.keys_pressed[input::Key::ESC] {
+                run = false;
+            }
+            for err in self.worker.errors() {
+                eprintln!("{}", err);
+            }
+            if input_map.keys_pressed[input::Key::N] {
+                self.worker.send(Command::Step);
+            }
+            if input_map.keys_pressed[input::Key::G] {
+                self.worker.send(Command::Advance(self.step_by));
+            }
+            if input_map.keys_pressed[input::Key::M] {
+                self.cycle_speed_mode();
+            }
+            if input_map.keys_pressed[input::Key::R] {
+                self.stats.rendering = !self.stats.rendering;
+            }
+            if input_map.keys_pressed[input::Key::L] {
+                self.cycle_rule();
+            }
+            if input_map.keys_pressed[input::Key::P] {
+                self.worker.send(Command::Save);
+            }
+            let (step_exponent, max_step_exponent) = {
+                let snapshot = self.worker.snapshot();
+                (snapshot.step_exponent, snapshot.max_step_exponent)
+            };
+            if input_map.keys_pressed[input::Key::PageUP] && step_exponent < max_step_exponent {
+                self.worker.send(Command::SetStepExponent(step_exponent + 1));
+            }
+            if input_map.keys_pressed[input::Key::PageDOWN] && step_exponent > 0 {
+                self.worker.send(Command::SetStepExponent(step_exponent - 1));
+            }
+            if input_map.keys_pressed[input::Key::SPACE] {
+                match self.worker.snapshot().running {
+                    true => self.worker.send(Command::Pause),
+                    false => self.worker.send(Command::Resume),
+                }
+            }
+
+            let shift = input_map.keys_hold[input::Key::LSHIFT];
+            if input_map.keys_pressed[input::Key::NumPLUS] {
+                self.change_speed(true, shift);
+            }
+            if input_map.keys_pressed[input::Key::NumMINUS] {
+                self.change_speed(false, shift);
+            }
+
+            let snapshot = self.worker.snapshot();
+            if self.stats.rendering {
+                let frame_duration = frame_timer.elapsed();
+                frame_timer = Instant::now();
+                self.view.update(&input_map, &frame_duration);
+                if snapshot.rule != self.rule {
+                    self.rule = snapshot.rule.clone();
+                    self.renderer.set_palette(&render::palette(&self.rule));
+                    self.renderer.set_hexagonal(self.rule.neighbourhood() == rule::Neighbourhood::Hexagonal);
+                }
+                self.renderer.render(&snapshot.words, &snapshot.states, &self.view);
+            }
+
+            fps_counter += 1;
+            if frame_counter_timer.elapsed().as_millis() >= 1000 {
+                self.stats.fps = fps_counter;
+                fps_counter = 0;
+                frame_counter_timer = Instant::now();
+            }
+
+            if let Err(err) = print_statistics(&self.stats, snapshot) {
+                eprintln!("Error printing Stats: \n\t{}", err);
+            }
+        }
+    }
+}
+
+fn print_statistics(stats : &Statistics, snapshot : &Snapshot) -> std::io::Result<()> {
+    use crossterm::*;
+    let rule = &snapshot.rule;
+    let mut stdout = stdout();
+    queue!(stdout, cursor::RestorePosition)?;
+    queue!(stdout, cursor::SavePosition)?;
+    queue!(stdout, style::Print("----------------------------Rusty Life---------------------------------\n"))?;
+    queue!(stdout, style::Print(format!("| speed: {}                         ", snapshot.speed)))?;
+    queue!(stdout, cursor::MoveToColumn(40))?;
+    queue!(stdout, style::Print(format!("board size: {}x{}                   ", stats.board_width, stats.board_height)))?;
+    queue!(stdout, cursor::MoveToColumn(71))?;
+    queue!(stdout, style::Print("|\n"))?;
+
+    queue!(stdout, style::Print(format!("| generation: {}                    ", snapshot.generation)))?;
+    queue!(stdout, cursor::MoveToColumn(40))?;
+    queue!(stdout, style::Print(format!("resolution: {}x{}                   ", stats.resolution_width, stats.resolution_height)))?;
+    queue!(stdout, cursor::MoveToColumn(71))?;
+    queue!(stdout, style::Print("|\n"))?;
+
+    queue!(stdout, style::Print(format!("| fps: {}                           ", stats.fps)))?;
+    queue!(stdout, cursor::MoveToColumn(40))?;
+    queue!(stdout, style::Print(format!("step: 2^{}                          ", snapshot.step_exponent)))?;
+    queue!(stdout, cursor::MoveToColumn(71))?;
+    queue!(stdout, style::Print("|\n"))?;
+
+    queue!(stdout, style::Print(format!("| rendering: {}                     ", stats.rendering)))?;
+    queue!(stdout, cursor::MoveToColumn(40))?;
+    queue!(stdout, style::Print(format!("population: {}                      ", snapshot.population)))?;
+    queue!(stdout, cursor::MoveToColumn(71))?;
+    queue!(stdout, style::Print("|\n"))?;
+
+    queue!(stdout, style::Print(format!("| status: {}                        ", if snapshot.running { SimStatus::RUNNING } else { SimStatus::PAUSED })))?;
+    queue!(stdout, cursor::MoveToColumn(71))?;
+    queue!(stdout, style::Print("|\n"))?;
+
+    queue!(stdout, style::Print(format!("| rule: {} {}                                        ",
+                                        rule,
+                                        rule.name().unwrap_or(""))))?;
+    queue!(stdout, cursor::MoveToColumn(71))?;
+    queue!(stdout, style::Print("|\n"))?;
+
+    if let Some(seed) = snapshot.seed {
+        queue!(stdout, style::Print(format!("| seed: {}                          ", seed)))?;
+        queue!(stdout, cursor::MoveToColumn(71))?;
+        queue!(stdout, style::Print("|\n"))?;
+    }
+
+    queue!(stdout, style::Print("-----------------------------------------------------------------------\n"))?;
+    stdout.flush()?;
+    Ok(())
+}
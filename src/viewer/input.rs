@@ -10,6 +10,7 @@ macro_rules! display_enum {
     (pub enum $name:ident {
         $($variant:ident),*,
     }) => {
+        #[allow(clippy::upper_case_acronyms)]
         pub enum $name {
             $($variant),*
         }
@@ -54,6 +55,10 @@ pub enum Key {
     R,
     N,
     F,
+    G,
+    L,
+    M,
+    P,
     I,
     LSHIFT,
     RSHIFT,
@@ -61,6 +66,8 @@ pub enum Key {
     ESC,
     NumPLUS,
     NumMINUS,
+    PageUP,
+    PageDOWN,
     MouseLeftButton,
     MouseRightButton,
     MouseWheelUp,
@@ -87,7 +94,7 @@ impl Input {
         let event_pump = sdl_context.event_pump().unwrap();
 
         Input {
-            event_pump : event_pump,
+            event_pump,
             input_map : InputMap{
                 keys_pressed : [false; KEY_MAP_SIZE],
                 keys_hold : [false; KEY_MAP_SIZE],
@@ -97,11 +104,11 @@ impl Input {
         }
     }
 
-    pub fn get_input_map(self : &Self) -> InputMap {
-        self.input_map.clone()
+    pub fn get_input_map(&self) -> InputMap {
+        self.input_map
     }
 
-    pub fn update_input(self : &mut Self) {
+    pub fn update_input(&mut self) {
         self.input_map.keys_pressed = [false; KEY_MAP_SIZE];
 
         for event in self.event_pump.poll_iter() {
@@ -115,11 +122,17 @@ impl Input {
                         (R,R),
                         (N,N),
                         (F,F),
+                        (G,G),
+                        (L,L),
+                        (M,M),
+                        (P,P),
                         (I,I),
                         (LShift, LSHIFT),
                         (RShift, RSHIFT),
                         (KpPlus, NumPLUS),
                         (KpMinus, NumMINUS),
+                        (PageUp, PageUP),
+                        (PageDown, PageDOWN),
                         (Space, SPACE),
                         (Escape, ESC)
                     );
@@ -133,11 +146,17 @@ impl Input {
                         (R,R),
                         (N,N),
                         (F,F),
+                        (G,G),
+                        (L,L),
+                        (M,M),
+                        (P,P),
                         (I,I),
                         (LShift, LSHIFT),
                         (RShift, RSHIFT),
                         (KpPlus, NumPLUS),
                         (KpMinus, NumMINUS),
+                        (PageUp, PageUP),
+                        (PageDown, PageDOWN),
                         (Space, SPACE),
                         (Escape, ESC)
                     );
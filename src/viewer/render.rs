@@ -2,8 +2,10 @@ extern crate sdl2;
 extern crate gl;
 extern crate nalgebra as na;
 
-use crate::rusty_life::input;
-use crate::rusty_life::view;
+use crate::viewer::input;
+use crate::viewer::view;
+
+use rusty_life::rule::Rule;
 
 use sdl2::*;
 
@@ -13,18 +15,24 @@ use std::ptr;
 pub struct Renderer {
     sdl_context : Sdl,
     sdl_window : sdl2::video::Window,
+    // Never read but has to outlive all GL calls
+    #[allow(dead_code)]
     gl_context : sdl2::video::GLContext,
     shader_program : gl::types::GLuint,
     vao : gl::types::GLuint,
     vbo_cells : gl::types::GLuint,
     vbo_indices : gl::types::GLuint,
+    /// States of the cells of a multi-state rule, four per `uint`
+    ssbo_states : gl::types::GLuint,
+    /// Colour of every state
+    ssbo_colors : gl::types::GLuint,
 }
 
 impl Drop for Renderer {
-    fn drop(self : &mut Self) {
+    fn drop(&mut self) {
         unsafe {
-            gl::DeleteBuffers(2,
-                [self.vbo_indices, self.vbo_cells].as_ptr());
+            gl::DeleteBuffers(4,
+                [self.vbo_indices, self.vbo_cells, self.ssbo_states, self.ssbo_colors].as_ptr());
             gl::DeleteVertexArrays(1, &self.vao);
         }
     }
@@ -55,13 +63,15 @@ impl Renderer {
         glattr.set_multisample_samples(8);
 
         let mut r = Renderer {
-            sdl_context : sdl_context,
-            sdl_window : sdl_window,
-            gl_context : gl_context,
+            sdl_context,
+            sdl_window,
+            gl_context,
             shader_program : 0,
             vao : 0,
             vbo_cells : 0,
             vbo_indices : 0,
+            ssbo_states : 0,
+            ssbo_colors : 0,
         };
 
         r.init_gl(num_rows, num_cols);
@@ -69,12 +79,11 @@ impl Renderer {
         r
     }
 
-    fn init_gl(self : &mut Self,
+    fn init_gl(&mut self,
                num_rows : u32, num_cols : u32) {
         unsafe {
             gl::Enable(gl::MULTISAMPLE);
             gl::Hint(gl::LINE_SMOOTH_HINT, gl::NICEST);
-            gl::ClearColor(0.25, 0.25, 0.25, 1.0);
 
             // Create Shader
             let file_content = String::from(r"
@@ -104,10 +113,19 @@ impl Renderer {
             layout(points) in;
             layout(triangle_strip, max_vertices = 64) out;
             layout(location = 2) uniform mat4 MVP;
+            layout(location = 3) uniform uint numCols;
+            layout(location = 4) uniform uint numStates;
+            layout(location = 5) uniform bool hexagonal;
+
+            // One byte per cell, only filled for rules with more than two states
+            layout(std430, binding = 0) readonly buffer States { uint states[]; };
+            layout(std430, binding = 1) readonly buffer Colors { vec4 colors[]; };
 
             in uint gs_cellStrip[]; // 16-bit
             in uvec2 gs_coordinate[];
 
+            out vec4 fs_color;
+
             const float aspect_ratio = 16f / 9.f;
 
 
@@ -120,12 +138,24 @@ impl Renderer {
 
                 float col = float(gs_coordinate[0].x) / 16.f;
                 col = col * (cellLength + cellGapLength) * 16.f - 1.f;
+                // Every row half a cell left of the one above it, which puts
+                // the six neighbours of a hexagonal rule around each cell
+                if(hexagonal) {
+                    col -= 0.5f * float(gs_coordinate[0].y) * (cellLength + cellGapLength);
+                }
                 float row = 1 - float(gs_coordinate[0].y) * (cellLength + cellGapLength);
 
                 vec4 cell_origin = vec4(col, row, 0, 1.f);
 
-                for(int i = 0; i < 16; ++i) {
-                    if(bool(gs_cellStrip[0] & m)) {
+                // The last strip of a row may extend past the board
+                for(uint i = 0; i < 16 && gs_coordinate[0].x + i < numCols; ++i) {
+                    uint state = uint(bool(gs_cellStrip[0] & m));
+                    if(numStates > 2) {
+                        uint idx = gs_coordinate[0].y * numCols + gs_coordinate[0].x + i;
+                        state = (states[idx / 4] >> (8 * (idx % 4))) & 0xff;
+                    }
+                    if(state != 0) {
+                        vec4 color = colors[state];
                         vec4 topLeft = cell_origin + cell_offset * float(i);
                         vec4 bottomLeft = topLeft +
                                           vec4(0, -cellLength, 0, 0);
@@ -135,12 +165,16 @@ impl Renderer {
                                           vec4(cellLength, 0, 0, 0);
 
                         gl_Position = MVP * topLeft;
+                        fs_color = color;
                         EmitVertex();
                         gl_Position = MVP * bottomLeft;
+                        fs_color = color;
                         EmitVertex();
                         gl_Position = MVP * topRight;
+                        fs_color = color;
                         EmitVertex();
                         gl_Position = MVP * bottomRight;
+                        fs_color = color;
                         EmitVertex();
                         EndPrimitive();
                     }
@@ -158,10 +192,11 @@ impl Renderer {
             let file_content = String::from(r"
             #version 430
 
+            in vec4 fs_color;
             out vec4 color;
 
             void main(void){
-                color = vec4(1.f);
+                color = fs_color;
             }
             ");
             let shader_source = ffi::CString::new(file_content.as_bytes()).unwrap();
@@ -181,7 +216,7 @@ impl Renderer {
 
             let mut coordinates : Vec<u32> = Vec::new();
             for row in 0..num_rows {
-                for col in 0..(num_cols / 16) { // sizeof(u16) == 16
+                for col in 0..num_cols.div_ceil(16) { // sizeof(u16) == 16
                     coordinates.push(col * 16);
                     coordinates.push(row);
                 }
@@ -211,16 +246,66 @@ impl Renderer {
 
             gl::BindBuffer(0, gl::ARRAY_BUFFER);
 
+            let mut ssbo = [0, 0];
+            gl::GenBuffers(2, ssbo.as_mut_ptr());
+            self.ssbo_states = ssbo[0];
+            self.ssbo_colors = ssbo[1];
+            gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, 0, self.ssbo_states);
+            gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, 1, self.ssbo_colors);
+            gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, self.ssbo_states);
+            gl::BufferData(gl::SHADER_STORAGE_BUFFER, 4, std::ptr::null(), gl::STREAM_DRAW);
+            gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, 0);
+
             // Don't unbind the VAO because we only have one in the application
             // Bind shader program only once for the same reason
             gl::UseProgram(self.shader_program);
+            gl::Uniform1ui(3, num_cols);
+        }
+        self.set_palette(&palette(&Rule::conway()));
+    }
+
+    /// Sets the colours of the states, index 0 being the dead state which
+    /// is the background. With more than two colours `render` expects the
+    /// state of every cell.
+    pub fn set_palette(&mut self, colors : &[[f32; 4]]) {
+        unsafe {
+            gl::ClearColor(colors[0][0], colors[0][1], colors[0][2], 1.0);
+            gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, self.ssbo_colors);
+            gl::BufferData(gl::SHADER_STORAGE_BUFFER,
+                           std::mem::size_of_val(colors) as isize,
+                           colors.as_ptr() as *const ffi::c_void,
+                           gl::STATIC_DRAW);
+            gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, 0);
+            gl::Uniform1ui(4, colors.len() as u32);
+        }
+    }
+
+    /// Shears the board into the offset rows of a hexagonal neighbourhood.
+    pub fn set_hexagonal(&mut self, hexagonal : bool) {
+        unsafe {
+            gl::Uniform1i(5, hexagonal as i32);
         }
     }
 
-    pub fn render(self : &mut Self, cells : &Vec<u16>, view : &view::OrthoView, dt : &std::time::Duration) {
+    /// Draws the alive `cells`, bit packed as by `Engine::fill_words`, or
+    /// with a palette of more than two colours the cells whose `states`
+    /// aren't 0.
+    pub fn render(&mut self, cells : &[u16], states : &[u8], view : &view::OrthoView) {
         unsafe {
             gl::Clear(gl::COLOR_BUFFER_BIT);
 
+            if !states.is_empty() {
+                // The shader reads the states as whole uints
+                let size = states.len().next_multiple_of(4);
+                gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, self.ssbo_states);
+                gl::BufferData(gl::SHADER_STORAGE_BUFFER, size as isize, std::ptr::null(), gl::STREAM_DRAW);
+                gl::BufferSubData(gl::SHADER_STORAGE_BUFFER,
+                                  0,
+                                  states.len() as isize,
+                                  states.as_ptr() as *const ffi::c_void);
+                gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, 0);
+            }
+
             // VAO already bound in init_gl()
             // Shader Program already bound in init_gl()
 
@@ -228,11 +313,11 @@ impl Renderer {
             // filling it with new data
             gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo_cells);
             gl::BufferData(gl::ARRAY_BUFFER,
-                           (cells.len() * std::mem::size_of::<u16>()) as isize,
+                           std::mem::size_of_val(cells) as isize,
                            std::ptr::null(),
                            gl::STREAM_DRAW);
             gl::BufferData(gl::ARRAY_BUFFER,
-                           (cells.len() * std::mem::size_of::<u16>()) as isize,
+                           std::mem::size_of_val(cells) as isize,
                            cells.as_ptr() as *const ffi::c_void,
                            gl::STREAM_DRAW);
             gl::BindBuffer(0, gl::ARRAY_BUFFER);
@@ -245,7 +330,7 @@ impl Renderer {
         self.sdl_window.gl_swap_window();
     }
 
-    pub fn create_input(self : &Self) -> input::Input {
+    pub fn create_input(&self) -> input::Input {
         input::Input::new(&self.sdl_context)
     }
 
@@ -288,4 +373,25 @@ impl Renderer {
             }
         }
     }
-}
\ No newline at end of file
+}
+
+/// Colours of the states of `rule`: alive cells are white on grey, dying
+/// ones fade from yellow to dark red as they age. A rule table may give
+/// other colours in its @COLORS section.
+pub fn palette(rule : &Rule) -> Vec<[f32; 4]> {
+    let dying = rule.states().saturating_sub(2) as usize;
+    let fade = |i : usize| {
+        let t = if dying > 1 { i as f32 / (dying - 1) as f32 } else { 0.0 };
+        [1.0 - 0.55 * t, 0.85 - 0.8 * t, 0.2 - 0.15 * t, 1.0]
+    };
+    let mut colors = vec![[0.25, 0.25, 0.25, 1.0], [1.0, 1.0, 1.0, 1.0]];
+    colors.extend((0..dying).map(fade));
+    if let Some(table) = rule.rule_table() {
+        for (state, color) in colors.iter_mut().enumerate() {
+            if let Some([r, g, b]) = table.color(state as u8) {
+                *color = [r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, 1.0];
+            }
+        }
+    }
+    colors
+}
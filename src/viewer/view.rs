@@ -1,6 +1,6 @@
 extern crate nalgebra as na;
 
-use crate::rusty_life::input;
+use crate::viewer::input;
 
 pub struct OrthoView {
     pub mvp : na::Matrix4<f32>,
@@ -13,7 +13,7 @@ impl OrthoView {
                                                 -(window_size.1 as f32)/2., window_size.1 as f32 /2.,
                                                 0., 1.);
         let mut r = OrthoView {
-            mvp : view_ortho.as_matrix().clone(),
+            mvp : *view_ortho.as_matrix(),
             view_ortho_mat : view_ortho,
         };
 
@@ -22,7 +22,7 @@ impl OrthoView {
         r
     }
 
-    pub fn update(self : &mut Self, input_map : &input::InputMap, dt : &std::time::Duration) {
+    pub fn update(&mut self, input_map : &input::InputMap, dt : &std::time::Duration) {
         let mut translation_speed = 2.; // pixels / second
         let mut scaling_speed = 1.05;
         let mut final_scaling = 1.;
@@ -76,8 +76,8 @@ impl OrthoView {
         self.mvp.append_translation_mut(&final_translation);
     }
 
-    fn reset_view(self : &mut Self) {
-        self.mvp = self.view_ortho_mat.as_matrix().clone();
+    fn reset_view(&mut self) {
+        self.mvp = *self.view_ortho_mat.as_matrix();
         self.mvp.append_translation_mut(&na::Vector3::new(-1., 1., 0.));
     }
 }
\ No newline at end of file
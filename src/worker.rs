@@ -0,0 +1,392 @@
+//! Simulation running on a background thread.
+//!
+//! The viewer controls the worker with `Command`s sent over a channel and
+//! draws the latest `Snapshot` it published. Commands that fail send their
+//! error back over a second channel. Long batches of steps are split into
+//! chunks, between which the worker picks up new commands and publishes
+//! its progress. Snapshots are passed through
+//! a triple buffer, so neither side ever waits for the other: a slow
+//! generation doesn't freeze the window and a slow frame doesn't hold up
+//! the simulation.
+
+use std::collections::VecDeque;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::engine::Rect;
+use crate::rule::Rule;
+use crate::simulation::Simulation;
+use crate::speed::{Pacer, Speed};
+
+/// Time between two frames of the worker at `Speed::StepsPerFrame`.
+const FRAME : Duration = Duration::from_micros(16_667);
+
+/// How long the worker waits for commands while no step is due.
+const POLL : Duration = Duration::from_millis(1);
+
+/// Longest time the worker steps before it looks for new commands.
+const CHUNK : Duration = Duration::from_millis(20);
+
+/// Requests to the worker, applied in the order they were sent.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Pause,
+    Resume,
+    /// Advances by one step of `2^step_exponent` generations
+    Step,
+    /// Advances by exactly this many generations. Later commands wait for
+    /// it to finish, a `Pause` stops it.
+    Advance(u64),
+    SetSpeed(Speed),
+    /// Fails if it exceeds the engine's `max_step_exponent()`
+    SetStepExponent(u32),
+    /// Fails if the engine can't simulate the rule
+    SetRule(Rule),
+    SetCell { col : i64, row : i64, value : bool },
+    /// Writes the alive part of the board to `rusty_life_<generation>.rle`
+    Save,
+    /// Ends the worker thread
+    Quit,
+}
+
+/// State of the simulation published after every batch of steps.
+#[derive(Debug, Clone, Default)]
+pub struct Snapshot {
+    pub generation : u64,
+    pub population : u64,
+    pub rule : Rule,
+    pub step_exponent : u32,
+    /// Largest step exponent the engine accepts
+    pub max_step_exponent : u32,
+    pub speed : Speed,
+    pub running : bool,
+    pub seed : Option<u64>,
+    /// Cells of the board area, packed as by `Engine::fill_words`
+    pub words : Vec<u16>,
+    /// State of every cell of the board area row by row, only filled for
+    /// rules with more than two states, i.e. Generations rules and rule
+    /// tables
+    pub states : Vec<u8>,
+}
+
+/// Writing end of a triple buffer. The writer fills its own buffer and
+/// swaps it with the shared middle one, the reader swaps the middle one
+/// with its own buffer whenever a newer one is there.
+struct Publisher<T> {
+    back : T,
+    /// The middle buffer and whether it is newer than the reader's one
+    middle : Arc<Mutex<(T, bool)>>,
+}
+
+impl<T> Publisher<T> {
+    fn publish(&mut self) {
+        let mut middle = self.middle.lock().unwrap();
+        std::mem::swap(&mut middle.0, &mut self.back);
+        middle.1 = true;
+    }
+}
+
+/// Reading end of a triple buffer, see `Publisher`.
+struct Subscriber<T> {
+    front : T,
+    middle : Arc<Mutex<(T, bool)>>,
+}
+
+impl<T> Subscriber<T> {
+    fn latest(&mut self) -> &T {
+        let mut middle = self.middle.lock().unwrap();
+        if middle.1 {
+            std::mem::swap(&mut middle.0, &mut self.front);
+            middle.1 = false;
+        }
+        &self.front
+    }
+}
+
+fn triple_buffer<T : Default>() -> (Publisher<T>, Subscriber<T>) {
+    let middle = Arc::new(Mutex::new((T::default(), false)));
+    (Publisher { back : T::default(), middle : middle.clone() },
+     Subscriber { front : T::default(), middle })
+}
+
+/// Handle of the worker thread owning a `Simulation`.
+pub struct Worker {
+    commands : Sender<Command>,
+    snapshots : Subscriber<Snapshot>,
+    errors : Receiver<String>,
+    thread : Option<JoinHandle<Simulation>>,
+}
+
+impl Worker {
+    /// Moves the simulation onto a new thread, advancing it at `speed`
+    /// right away if `running`.
+    pub fn spawn(sim : Simulation, speed : Speed, running : bool) -> Worker {
+        let (commands, receiver) = mpsc::channel();
+        let (errors, error_receiver) = mpsc::channel();
+        let (publisher, snapshots) = triple_buffer();
+        let state = WorkerState {
+            sim,
+            commands : receiver,
+            snapshots : publisher,
+            errors,
+            pacer : Pacer::new(speed, Instant::now()),
+            running,
+            next_frame : Instant::now(),
+            steps_left : 0,
+            advance_left : 0,
+            deferred : VecDeque::new(),
+        };
+        Worker {
+            commands,
+            snapshots,
+            errors : error_receiver,
+            thread : Some(thread::spawn(move || state.run())),
+        }
+    }
+
+    /// Queues a command, ignored if the worker already ended.
+    pub fn send(&self, command : Command) {
+        let _ = self.commands.send(command);
+    }
+
+    /// The most recent state published by the worker.
+    pub fn snapshot(&mut self) -> &Snapshot {
+        self.snapshots.latest()
+    }
+
+    /// Errors of the commands that failed since the last call, oldest
+    /// first.
+    pub fn errors(&self) -> Vec<String> {
+        self.errors.try_iter().collect()
+    }
+
+    /// Ends the worker once it applied the commands sent so far and hands
+    /// back the simulation.
+    pub fn stop(mut self) -> Simulation {
+        self.send(Command::Quit);
+        self.thread.take().unwrap().join().expect("The simulation thread panicked")
+    }
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            self.send(Command::Quit);
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Everything the worker thread owns.
+struct WorkerState {
+    sim : Simulation,
+    commands : Receiver<Command>,
+    snapshots : Publisher<Snapshot>,
+    errors : Sender<String>,
+    pacer : Pacer,
+    running : bool,
+    /// Earliest time of the next batch at `Speed::StepsPerFrame`
+    next_frame : Instant,
+    /// Steps left of the current batch
+    steps_left : u64,
+    /// Generations left of the current `Advance`
+    advance_left : u64,
+    /// Commands received during an `Advance`, applied after it
+    deferred : VecDeque<Command>,
+}
+
+impl WorkerState {
+    fn run(mut self) -> Simulation {
+        self.publish();
+        loop {
+            if self.advance_left > 0 {
+                if !self.defer_commands() {
+                    return self.sim;
+                }
+                self.advance_chunk();
+                self.publish();
+                continue;
+            }
+
+            // Block while paused, otherwise only pick up pending commands
+            let command = match self.deferred.pop_front() {
+                Some(command) => Some(command),
+                None if self.running => match self.commands.try_recv() {
+                    Ok(command) => Some(command),
+                    Err(TryRecvError::Empty) => None,
+                    Err(TryRecvError::Disconnected) => return self.sim,
+                },
+                None => match self.commands.recv() {
+                    Ok(command) => Some(command),
+                    Err(_) => return self.sim,
+                },
+            };
+            if let Some(command) = command {
+                if !self.apply(command) {
+                    return self.sim;
+                }
+                continue;
+            }
+
+            if self.steps_left == 0 {
+                let now = Instant::now();
+                let steps = match self.pacer.speed() {
+                    Speed::StepsPerFrame(_) if now < self.next_frame => 0,
+                    _ => self.pacer.steps_due(now, 1 << self.sim.step_exponent()),
+                };
+                if steps == 0 {
+                    let timeout = match self.pacer.speed() {
+                        Speed::StepsPerFrame(_) => self.next_frame.saturating_duration_since(now),
+                        _ => POLL,
+                    };
+                    match self.commands.recv_timeout(timeout) {
+                        Ok(command) => {
+                            if !self.apply(command) {
+                                return self.sim;
+                            }
+                        }
+                        Err(RecvTimeoutError::Disconnected) => return self.sim,
+                        Err(RecvTimeoutError::Timeout) => (),
+                    }
+                    continue;
+                }
+                self.next_frame = now + FRAME;
+                self.steps_left = steps;
+            }
+            self.step_chunk();
+            self.publish();
+        }
+    }
+
+    /// Takes the steps left of the current batch until `CHUNK` is used up.
+    fn step_chunk(&mut self) {
+        let start = Instant::now();
+        while self.steps_left > 0 {
+            if let Err(err) = self.sim.step() {
+                self.running = false;
+                self.steps_left = 0;
+                self.report(Err(err));
+                return;
+            }
+            self.steps_left -= 1;
+            if start.elapsed() >= CHUNK {
+                return;
+            }
+        }
+    }
+
+    /// Advances by the generations left of an `Advance` until `CHUNK` is
+    /// used up, in steps of the usual size and the rest like
+    /// `Simulation::advance`.
+    fn advance_chunk(&mut self) {
+        let start = Instant::now();
+        let step_size = 1_u64 << self.sim.step_exponent();
+        let result = loop {
+            if self.advance_left < step_size {
+                let result = self.sim.advance(self.advance_left);
+                self.advance_left = 0;
+                break result;
+            }
+            if let Err(err) = self.sim.step() {
+                break Err(err);
+            }
+            self.advance_left -= step_size;
+            if self.advance_left == 0 || start.elapsed() >= CHUNK {
+                break Ok(());
+            }
+        };
+        if result.is_err() {
+            self.advance_left = 0;
+        }
+        self.report(result);
+    }
+
+    /// Picks up the commands sent during an `Advance`. They wait for it to
+    /// finish in order, a `Pause` stops it right away. Returns `false` once
+    /// the handle is gone.
+    fn defer_commands(&mut self) -> bool {
+        loop {
+            match self.commands.try_recv() {
+                Ok(command) => {
+                    if command == Command::Pause {
+                        self.advance_left = 0;
+                    }
+                    self.deferred.push_back(command);
+                }
+                Err(TryRecvError::Empty) => return true,
+                Err(TryRecvError::Disconnected) => return false,
+            }
+        }
+    }
+
+    /// Applies the command and publishes the result, `false` on `Quit`.
+    fn apply(&mut self, command : Command) -> bool {
+        match command {
+            Command::Pause => {
+                self.running = false;
+                self.steps_left = 0;
+            }
+            Command::Resume => {
+                self.running = true;
+                self.pacer.reset(Instant::now());
+            }
+            Command::Step => {
+                let result = self.sim.step();
+                self.report(result);
+            }
+            Command::Advance(generations) => self.advance_left = generations,
+            Command::SetSpeed(speed) => self.pacer.set_speed(speed),
+            Command::SetStepExponent(exponent) => {
+                let result = self.sim.set_step_exponent(exponent);
+                self.report(result);
+            }
+            Command::SetRule(rule) => {
+                let result = self.sim.set_rule(rule).map_err(|err| format!("Error switching rule: {}", err));
+                self.report(result);
+            }
+            Command::SetCell { col, row, value } => self.sim.engine_mut().set_cell(col, row, value),
+            Command::Save => {
+                let filepath = format!("rusty_life_{}.rle", self.sim.generation());
+                let result = self.sim.save_rle(&filepath)
+                                     .map_err(|err| format!("Error writing RLE file \"{}\": {}", filepath, err));
+                self.report(result);
+            }
+            Command::Quit => return false,
+        }
+        self.publish();
+        true
+    }
+
+    /// Sends the error of a failed command back to `Worker::errors`.
+    fn report(&self, result : Result<(), String>) {
+        if let Err(err) = result {
+            // Nobody listens once the handle is gone
+            let _ = self.errors.send(err);
+        }
+    }
+
+    fn publish(&mut self) {
+        let (width, height) = self.sim.board_size();
+        let snapshot = &mut self.snapshots.back;
+        snapshot.generation = self.sim.generation();
+        snapshot.population = self.sim.engine().population();
+        snapshot.rule = self.sim.rule();
+        snapshot.step_exponent = self.sim.step_exponent();
+        snapshot.max_step_exponent = self.sim.engine().max_step_exponent();
+        snapshot.speed = self.pacer.speed();
+        snapshot.running = self.running;
+        snapshot.seed = self.sim.seed();
+        snapshot.words.resize(width.div_ceil(16) * height, 0);
+        let board = Rect { col : 0, row : 0, width : width as i64, height : height as i64 };
+        self.sim.engine().fill_words(board, &mut snapshot.words);
+        if snapshot.rule.states() > 2 {
+            snapshot.states.resize(width * height, 0);
+            self.sim.engine().fill_states(board, &mut snapshot.states);
+        } else {
+            snapshot.states.clear();
+        }
+        self.snapshots.publish();
+    }
+}
@@ -0,0 +1,86 @@
+//! Fixtures shared by the tests that cross-check the grid against naive
+//! steppers: random topologies, the cells of a grid and a board stepped
+//! one cell at a time.
+
+// Every test file uses only some of them
+#![allow(dead_code)]
+
+use proptest::prelude::*;
+
+use rusty_life::grid::{Grid, Topology};
+
+/// Positions of the 8 neighbours of the Moore neighbourhood relative to
+/// the cell, row by row.
+pub const MOORE : [(isize, isize); 8] = [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)];
+
+pub fn topology() -> impl Strategy<Value = Topology> {
+    prop_oneof![
+        Just(Topology::Torus),
+        Just(Topology::Plane),
+        Just(Topology::KleinBottle),
+        Just(Topology::CrossSurface),
+    ]
+}
+
+/// Alive cells of the grid as (col, row) pairs, row by row.
+pub fn alive(grid : &Grid) -> Vec<(usize, usize)> {
+    (0..grid.num_rows).flat_map(|row| (0..grid.num_cols).map(move |col| (col, row)))
+                      .filter(|(col, row)| grid.get_cell(*col, *row))
+                      .collect()
+}
+
+/// Board of `width` x `height` cells stored one state per cell, row by row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Board {
+    pub width : usize,
+    pub height : usize,
+    pub states : Vec<u8>,
+}
+
+impl Board {
+    pub fn from_grid(grid : &Grid) -> Board {
+        let states = (0..grid.num_rows)
+            .flat_map(|row| (0..grid.num_cols).map(move |col| (col, row)))
+            .map(|(col, row)| grid.get_state(col, row))
+            .collect();
+        Board { width : grid.num_cols, height : grid.num_rows, states }
+    }
+
+    /// Next generation, looking at every cell on its own. `next` gets the
+    /// state of a cell and the states around it: called with an offset
+    /// (dx, dy) they return the state of the cell there, with the edges
+    /// glued as given by `topology` and dead cells beyond them.
+    pub fn step<F>(&self, topology : Topology, next : F) -> Board
+        where F: Fn(u8, &dyn Fn(isize, isize) -> u8) -> u8 {
+        let mut stepped = self.clone();
+        for row in 0..self.height {
+            for col in 0..self.width {
+                let at = |dx : isize, dy : isize| {
+                    topology.map(col as isize + dx, row as isize + dy, self.width, self.height)
+                            .map_or(0, |(c, r)| self.states[r * self.width + c])
+                };
+                stepped.states[row * self.width + col] = next(self.states[row * self.width + col], &at);
+            }
+        }
+        stepped
+    }
+}
+
+/// Number of alive cells at `offsets` around a cell, see `Board::step`.
+pub fn count_alive<'a, I>(at : &dyn Fn(isize, isize) -> u8, offsets : I) -> u32
+    where I: IntoIterator<Item = &'a (isize, isize)> {
+    offsets.into_iter().filter(|(dx, dy)| at(*dx, *dy) == 1).count() as u32
+}
+
+/// Next state of a cell of a Generations rule with `states` states: dead
+/// cells may be born, alive ones may survive and all others fade through
+/// the dying states.
+pub fn generations_state(state : u8, born : bool, survives : bool, states : u16) -> u8 {
+    match state {
+        0 if born => 1,
+        0 => 0,
+        1 if survives => 1,
+        _ if state as u16 + 1 < states => state + 1,
+        _ => 0,
+    }
+}
@@ -0,0 +1,107 @@
+//! Generations rules: parsing and a cross-check of the bit planes of the
+//! dying cells against a naive stepper keeping one state per cell.
+
+mod common;
+
+use proptest::prelude::*;
+
+use common::{count_alive, generations_state, topology, Board, MOORE};
+use rusty_life::grid::{Grid, Topology};
+use rusty_life::hashlife::HashLife;
+use rusty_life::rule::Rule;
+use rusty_life::sparse::SparseGrid;
+
+/// Next generation under the Generations rule with the given birth and
+/// survival masks and number of states.
+fn step(board : &Board, birth : u16, survival : u16, states : u16, topology : Topology) -> Board {
+    board.step(topology, |state, at| {
+        let neighbours = count_alive(at, &MOORE);
+        generations_state(state, (birth >> neighbours) & 1 == 1, (survival >> neighbours) & 1 == 1, states)
+    })
+}
+
+#[test]
+fn parses_generations_rules() {
+    let brians_brain = "/2/3".parse::<Rule>().unwrap();
+    assert_eq!(brians_brain, Rule::generations(1 << 2, 0, 3));
+    assert_eq!(brians_brain.to_string(), "B2/S/C3");
+    assert_eq!("B2/S/C3".parse::<Rule>().as_ref(), Ok(&brians_brain));
+    assert_eq!(brians_brain.name(), Some("Brian's Brain"));
+
+    let star_wars = "345/2/4".parse::<Rule>().unwrap();
+    assert_eq!(star_wars.to_string(), "B2/S345/C4");
+    assert_eq!(star_wars.states(), 4);
+
+    // Two states are plain Life-like rules
+    assert_eq!("B3/S23/C2".parse::<Rule>(), Ok(Rule::conway()));
+    assert_eq!(Rule::conway().to_string(), "B3/S23");
+    assert!("B3/S23/C1".parse::<Rule>().is_err());
+    assert!("B3/S23/C257".parse::<Rule>().is_err());
+}
+
+#[test]
+fn brians_brain_glider_moves() {
+    // The smallest spaceship of Brian's Brain, moving up by one cell per
+    // generation: two alive cells side by side with two dying cells below
+    let rule = "/2/3".parse::<Rule>().unwrap();
+    let mut grid = Grid::new((20, 20), rule, Topology::Plane);
+    for (col, row, state) in [(9, 10, 1), (10, 10, 1), (9, 11, 2), (10, 11, 2)] {
+        grid.set_state(col, row, state);
+    }
+    for _ in 0..4 {
+        grid.run_lifecycle();
+    }
+    let expected = [(9, 6, 1), (10, 6, 1), (9, 7, 2), (10, 7, 2)];
+    for row in 0..20 {
+        for col in 0..20 {
+            let state = expected.iter()
+                                .find(|(c, r, _)| (*c, *r) == (col, row))
+                                .map_or(0, |(_, _, s)| *s);
+            assert_eq!(grid.get_state(col, row), state, "cell ({}, {})", col, row);
+        }
+    }
+}
+
+#[test]
+fn other_engines_reject_generations_rules() {
+    let rule = "345/2/4".parse::<Rule>().unwrap();
+    assert!(HashLife::new(rule.clone()).is_err());
+    assert!(SparseGrid::new(rule).is_err());
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn kernel_matches_reference(width in 1..300_u32,
+                                height in 1..200_u32,
+                                topology in topology(),
+                                birth in 0..0x200_u16,
+                                survival in 0..0x200_u16,
+                                states in 3..=40_u16,
+                                seed in any::<u64>(),
+                                percent in 1..=100_u64,
+                                generations in 1..10_usize) {
+        let mut grid = Grid::new((width, height), Rule::generations(birth, survival, states), topology);
+        // `percent` of the cells in any state, dying ones included, so
+        // that sparse boards leave tiles untouched
+        let mut random = seed | 1;
+        for row in 0..height as usize {
+            for col in 0..width as usize {
+                random ^= random << 13;
+                random ^= random >> 7;
+                random ^= random << 17;
+                if random % 100 < percent {
+                    grid.set_state(col, row, ((random >> 8) % states as u64) as u8);
+                }
+            }
+        }
+
+        let mut board = Board::from_grid(&grid);
+        for _ in 0..generations {
+            grid.run_lifecycle();
+            board = step(&board, birth, survival, states, topology);
+            prop_assert_eq!(Board::from_grid(&grid), board.clone());
+        }
+    }
+}
@@ -0,0 +1,49 @@
+//! Headless runs ending on extinction or once the board repeats itself.
+
+use rusty_life::grid::{Grid, Topology};
+use rusty_life::headless::{run, Options, Until};
+use rusty_life::rule::Rule;
+use rusty_life::Simulation;
+
+fn options(generations : u64, until : Until) -> Options {
+    Options { generations, until : Some(until), stats_interval : 0, output : None }
+}
+
+#[test]
+fn finds_periods() {
+    // A glider on a torus is back at its start after crossing it 4 times
+    // in 4 generations each
+    let mut grid = Grid::new((20, 20), Rule::conway(), Topology::Torus);
+    for &(col, row) in &[(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)] {
+        grid.set_cell(col, row, true);
+    }
+    let mut sim = Simulation::from_grid(grid);
+    let report = run(&mut sim, &options(10_000, Until::Stable)).unwrap();
+    assert_eq!(report.period, Some(80));
+    assert!(report.generation < 400, "{}", report.generation);
+
+    // Three cells turn into a block next to a blinker
+    let mut grid = Grid::new((20, 20), Rule::conway(), Topology::Plane);
+    for &(col, row) in &[(2, 2), (3, 2), (2, 3), (10, 10), (11, 10), (12, 10)] {
+        grid.set_cell(col, row, true);
+    }
+    let mut sim = Simulation::from_grid(grid);
+    let report = run(&mut sim, &options(10_000, Until::Stable)).unwrap();
+    assert_eq!(report.period, Some(2));
+    assert_eq!(report.population, 7);
+
+    // Runs end at the generation limit without a period
+    let report = run(&mut sim, &options(10, Until::Extinct)).unwrap();
+    assert_eq!(report.period, None);
+    assert_eq!(report.generation, sim.generation());
+}
+
+#[test]
+fn stops_on_extinction() {
+    let mut grid = Grid::new((20, 20), Rule::conway(), Topology::Plane);
+    grid.set_cell(5, 5, true);
+    grid.set_cell(6, 5, true);
+    let mut sim = Simulation::from_grid(grid);
+    let report = run(&mut sim, &options(100, Until::Extinct)).unwrap();
+    assert_eq!((report.generation, report.population), (1, 0));
+}
@@ -0,0 +1,232 @@
+//! Isotropic non-totalistic rules in Hensel notation: the configurations
+//! behind the letters, patterns whose fate depends on them and a
+//! cross-check of the lookup table kernel against a naive stepper.
+
+mod common;
+
+use proptest::prelude::*;
+
+use common::{alive, topology, Board};
+use rusty_life::engine::Engine;
+use rusty_life::grid::{Grid, Topology};
+use rusty_life::hashlife::HashLife;
+use rusty_life::rule::Rule;
+use rusty_life::soup::Soup;
+use rusty_life::sparse::SparseGrid;
+
+const LETTERS : [&str; 9] = ["", "ce", "cekain", "cekainyqjr", "cekainyqjrtwz", "cekainyqjr", "cekain", "ce", ""];
+
+/// Index into `Rule::table` of a neighbourhood drawn as three rows of
+/// 'X' for alive and '.' for dead cells.
+fn neighbourhood(rows : [&str; 3]) -> usize {
+    rows.concat().chars().fold(0, |idx, c| (idx << 1) | (c == 'X') as usize)
+}
+
+fn is_born(rule : &str, neighbourhood : usize) -> bool {
+    let table = rule.parse::<Rule>().unwrap().table();
+    (table[neighbourhood / 64] >> (neighbourhood % 64)) & 1 == 1
+}
+
+/// The neighbourhood rotated by 90 degrees or mirrored left to right.
+fn rotate(idx : usize) -> usize {
+    (0..9).filter(|bit| idx & (1 << (8 - bit)) != 0)
+          .map(|bit| { let (row, col) = (bit / 3, bit % 3); 1 << (8 - (3 * col + 2 - row)) })
+          .sum()
+}
+
+fn mirror(idx : usize) -> usize {
+    (0..9).filter(|bit| idx & (1 << (8 - bit)) != 0)
+          .map(|bit| { let (row, col) = (bit / 3, bit % 3); 1 << (8 - (3 * row + 2 - col)) })
+          .sum()
+}
+
+#[test]
+fn letters_partition_the_neighbourhoods() {
+    for (n, letters) in LETTERS.iter().enumerate() {
+        let rules = if letters.is_empty() {
+            vec![format!("B{}/S", n)]
+        } else {
+            letters.chars().map(|letter| format!("B{}{}/S", n, letter)).collect()
+        };
+        let neighbourhoods = (0..512_usize).filter(|idx| idx & 0b10000 == 0 && idx.count_ones() as usize == n);
+        let mut covered = 0;
+        for idx in neighbourhoods {
+            let births = rules.iter().filter(|rule| is_born(rule, idx)).count();
+            assert_eq!(births, 1, "neighbourhood {:09b} with {} neighbours", idx, n);
+            covered += 1;
+            // Rotations and reflections don't change the configuration
+            for rule in rules.iter().filter(|rule| is_born(rule, idx)) {
+                assert!(is_born(rule, rotate(idx)) && is_born(rule, mirror(idx)), "{} isn't isotropic", rule);
+            }
+        }
+        assert!(rules.iter().all(|rule| (0..512).any(|idx| is_born(rule, idx))));
+        assert_eq!(covered, (0..n).fold(1, |c, k| c * (8 - k) / (k + 1)));
+    }
+}
+
+#[test]
+fn letters_match_their_shapes() {
+    let shapes = [
+        ("1c", ["X..", "...", "..."]),
+        ("1e", [".X.", "...", "..."]),
+        ("2c", ["X.X", "...", "..."]),
+        ("2e", [".X.", "X..", "..."]),
+        ("2k", ["X..", "..X", "..."]),
+        ("2a", ["XX.", "...", "..."]),
+        ("2i", ["...", "X.X", "..."]),
+        ("2n", ["X..", "...", "..X"]),
+        ("3c", ["X.X", "...", "X.."]),
+        ("3e", [".X.", "X.X", "..."]),
+        ("3a", ["XX.", "X..", "..."]),
+        ("3i", ["XXX", "...", "..."]),
+        ("4c", ["X.X", "...", "X.X"]),
+        ("4e", [".X.", "X.X", ".X."]),
+        ("7c", [".XX", "X.X", "XXX"]),
+        ("7e", ["X.X", "X.X", "XXX"]),
+    ];
+    for (condition, shape) in shapes.iter() {
+        assert!(is_born(&format!("B{}/S", condition), neighbourhood(*shape)), "{}", condition);
+    }
+}
+
+#[test]
+fn parses_and_displays_hensel_notation() {
+    let rule = "B2-a/S12".parse::<Rule>().unwrap();
+    assert!(!rule.is_totalistic());
+    assert_eq!(rule.to_string(), "B2-a/S12");
+    assert_eq!("B3/S23-a4i".parse::<Rule>().unwrap().to_string(), "B3/S23-a4i");
+    assert_eq!("b3/s2kaIN".parse::<Rule>().unwrap().to_string(), "B3/S2-ce");
+    // Listing every configuration of a count is the totalistic rule
+    assert_eq!("B3cekainyqjr/S2cekain3".parse::<Rule>(), Ok(Rule::conway()));
+    assert_eq!("B2-a/S12/C4".parse::<Rule>().unwrap().to_string(), "B2-a/S12/C4");
+    assert!("B2x/S".parse::<Rule>().is_err());
+    assert!("B1k/S".parse::<Rule>().is_err());
+    assert!("B2-/S".parse::<Rule>().is_err());
+}
+
+#[test]
+fn blinker_depends_on_2i_and_3i() {
+    // The middle cell of a blinker has two opposite neighbours (2i), the
+    // cells born beside it see one full side of their neighbourhood (3i)
+    let blinker = |rule : &str| {
+        let mut grid = Grid::new((10, 10), rule.parse().unwrap(), Topology::Plane);
+        for col in 4..7 {
+            grid.set_cell(col, 5, true);
+        }
+        grid
+    };
+
+    let mut grid = blinker("B3i/S2i");
+    grid.run_lifecycle();
+    assert_eq!(alive(&grid), vec![(5, 4), (5, 5), (5, 6)]);
+    grid.run_lifecycle();
+    assert_eq!(alive(&grid), vec![(4, 5), (5, 5), (6, 5)]);
+
+    let mut grid = blinker("B3-i/S23");
+    grid.run_lifecycle();
+    assert_eq!(alive(&grid), vec![(5, 5)]);
+    grid.run_lifecycle();
+    assert!(alive(&grid).is_empty());
+}
+
+#[test]
+fn block_depends_on_3a() {
+    // Every cell of a block sees a corner and both edges next to it
+    let block = |rule : &str| {
+        let mut grid = Grid::new((8, 8), rule.parse().unwrap(), Topology::Torus);
+        for (col, row) in [(3, 3), (4, 3), (3, 4), (4, 4)] {
+            grid.set_cell(col, row, true);
+        }
+        grid.run_lifecycle();
+        alive(&grid)
+    };
+    assert_eq!(block("B3/S3a"), vec![(3, 3), (4, 3), (3, 4), (4, 4)]);
+    assert!(block("B3/S23-a").is_empty());
+}
+
+#[test]
+fn engines_agree() {
+    let rule = "B2-a3/S12-k3ai".parse::<Rule>().unwrap();
+    let size = 160;
+    let mut grid = Grid::new((size, size), rule.clone(), Topology::Plane);
+    let mut hashlife = HashLife::new(rule.clone()).unwrap();
+    let mut sparse = SparseGrid::new(rule.clone()).unwrap();
+    let mut soup = Grid::new((size, size), rule, Topology::Plane);
+    Soup::new(11).fill(&mut soup, (30, 30));
+    for (col, row) in alive(&soup) {
+        let (col, row) = (col + 65, row + 65);
+        grid.set_cell(col, row, true);
+        Engine::set_cell(&mut hashlife, col as i64, row as i64, true);
+        Engine::set_cell(&mut sparse, col as i64, row as i64, true);
+    }
+    // Few enough generations that nothing reaches the border of the grid
+    for _ in 0..16 {
+        grid.run_lifecycle();
+        Engine::step(&mut hashlife, 0).unwrap();
+        Engine::step(&mut sparse, 0).unwrap();
+    }
+    let cells = |engine : &dyn Engine| {
+        (0..size as i64).flat_map(|row| (0..size as i64).map(move |col| (col, row)))
+                        .filter(|(col, row)| engine.get_cell(*col, *row))
+                        .count()
+    };
+    assert_eq!(cells(&hashlife), grid.population() as usize);
+    assert_eq!(cells(&sparse), grid.population() as usize);
+    for (col, row) in alive(&grid) {
+        assert!(hashlife.get_cell(col as i64, row as i64) && sparse.get_cell(col as i64, row as i64));
+    }
+}
+
+/// Next generation looking up each cell in `table` on its own.
+fn step(board : &Board, table : &[u64; 8], topology : Topology) -> Board {
+    board.step(topology, |_, at| {
+        let idx = (-1..=1).flat_map(|dy| (-1..=1).map(move |dx| (dx, dy)))
+                          .fold(0, |idx, (dx, dy)| (idx << 1) | at(dx, dy) as usize);
+        ((table[idx / 64] >> (idx % 64)) & 1) as u8
+    })
+}
+
+/// Rule including each configuration with the corresponding bit of `bits`.
+fn random_rule(bits : u128) -> String {
+    let mut bit = 0;
+    let mut conditions = |letters : &[&str]| {
+        let mut s = String::new();
+        for (n, letters) in letters.iter().enumerate() {
+            let letters = if letters.is_empty() { " " } else { letters };
+            let chosen = letters.chars().filter(|_| { bit += 1; (bits >> (bit - 1)) & 1 == 1 }).collect::<String>();
+            if !chosen.is_empty() {
+                s += &format!("{}{}", n, chosen.trim());
+            }
+        }
+        s
+    };
+    let birth = conditions(&LETTERS);
+    let survival = conditions(&LETTERS);
+    format!("B{}/S{}", birth, survival)
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn kernel_matches_reference(width in 1..200_u32,
+                                height in 1..150_u32,
+                                topology in topology(),
+                                rule in any::<u128>(),
+                                seed in any::<u64>(),
+                                density in 0.0..1.0_f64,
+                                generations in 1..5_usize) {
+        let rule = random_rule(rule).parse::<Rule>().unwrap();
+        let mut grid = Grid::new((width, height), rule.clone(), topology);
+        let soup = Soup { density, ..Soup::new(seed) };
+        soup.fill(&mut grid, (width as usize, height as usize));
+
+        let table = rule.table();
+        let mut board = Board::from_grid(&grid);
+        for _ in 0..generations {
+            grid.run_lifecycle();
+            board = step(&board, &table, topology);
+            prop_assert_eq!(Board::from_grid(&grid), board.clone());
+        }
+    }
+}
@@ -0,0 +1,151 @@
+//! Larger than Life rules: parsing, Life as a radius 1 rule and a
+//! cross-check of the summed-area counts against a naive stepper that
+//! visits every cell of every neighbourhood.
+
+mod common;
+
+use proptest::prelude::*;
+
+use common::{count_alive, generations_state, topology, Board};
+use rusty_life::grid::{Grid, Topology};
+use rusty_life::hashlife::HashLife;
+use rusty_life::rule::{LargerThanLife, Neighbourhood, Rule};
+use rusty_life::soup::Soup;
+use rusty_life::sparse::SparseGrid;
+
+/// Next generation counting the cells of every neighbourhood one by one.
+fn step(board : &Board, rule : &LargerThanLife, states : u16, topology : Topology) -> Board {
+    let r = rule.radius as isize;
+    let inside = |dx : isize, dy : isize| match rule.neighbourhood {
+        Neighbourhood::Moore => true,
+        Neighbourhood::VonNeumann => dx.abs() + dy.abs() <= r,
+        Neighbourhood::Circular => dx * dx + dy * dy <= r * r + r,
+        Neighbourhood::Hexagonal => dx.abs() <= r && dy.abs() <= r && (dx - dy).abs() <= r,
+    };
+    let offsets = (-r..=r).flat_map(|dy| (-r..=r).map(move |dx| (dx, dy)))
+                          .filter(|&(dx, dy)| inside(dx, dy) && (rule.middle || (dx, dy) != (0, 0)))
+                          .collect::<Vec<_>>();
+    let within = |count : u32, (min, max) : (u32, u32)| count >= min && count <= max;
+    board.step(topology, |state, at| {
+        let count = count_alive(at, &offsets);
+        generations_state(state, within(count, rule.birth), within(count, rule.survival), states)
+    })
+}
+
+#[test]
+fn parses_larger_than_life_rules() {
+    let bosco = "R5,C0,M1,S34..58,B34..45,NM".parse::<Rule>().unwrap();
+    assert_eq!(bosco.larger_than_life(), Some(LargerThanLife {
+        radius : 5,
+        middle : true,
+        survival : (34, 58),
+        birth : (34, 45),
+        neighbourhood : Neighbourhood::Moore,
+    }));
+    assert_eq!(bosco.states(), 2);
+    assert_eq!(bosco.to_string(), "R5,C0,M1,S34..58,B34..45,NM");
+    assert_eq!(bosco.name(), Some("Bosco's Rule"));
+
+    let rule = "r3,c4,m0,s2..9,b5..7,nc".parse::<Rule>().unwrap();
+    assert_eq!(rule.states(), 4);
+    assert_eq!(rule.to_string(), "R3,C4,M0,S2..9,B5..7,NC");
+    let rule = "R2,C0,M1,S3..6,B4..5,NH".parse::<Rule>().unwrap();
+    assert_eq!(rule.neighbourhood(), Neighbourhood::Hexagonal);
+    assert_eq!(rule.to_string(), "R2,C0,M1,S3..6,B4..5,NH");
+    assert_eq!("R2,S1..3,B2..2".parse::<Rule>().unwrap().to_string(), "R2,C0,M0,S1..3,B2..2,NM");
+
+    assert!("R0,C0,M1,S1..2,B1..2,NM".parse::<Rule>().is_err());
+    assert!("R501,C0,M1,S1..2,B1..2,NM".parse::<Rule>().is_err());
+    assert!("R5,C0,M1,S1..2,B1..2,NX".parse::<Rule>().is_err());
+    assert!("R5,C0,M1,S1-2,B1..2,NM".parse::<Rule>().is_err());
+    assert!("R5,C0,M1,S1..2".parse::<Rule>().is_err());
+}
+
+#[test]
+fn radius_one_is_life() {
+    // With the middle cell counted an alive cell survives with 3 or 4
+    let ltl = "R1,C0,M1,S3..4,B3..3,NM".parse::<Rule>().unwrap();
+    for topology in [Topology::Torus, Topology::Plane, Topology::KleinBottle, Topology::CrossSurface] {
+        let mut life = Grid::new((150, 130), Rule::conway(), topology);
+        Soup::new(5).fill(&mut life, (150, 130));
+        let mut grid = Grid::new((150, 130), ltl.clone(), topology);
+        Soup::new(5).fill(&mut grid, (150, 130));
+        for _ in 0..20 {
+            life.run_lifecycle();
+            grid.run_lifecycle();
+        }
+        assert_eq!(Board::from_grid(&grid), Board::from_grid(&life), "{}", topology);
+    }
+}
+
+#[test]
+fn switching_rules_recounts() {
+    // The counts are kept between generations, also across rule changes
+    let rules = ["R2,C0,M1,S3..8,B4..6,NM", "R3,C0,M0,S6..14,B7..10,NC", "R3,C0,M0,S6..14,B7..10,NN",
+                 "R1,C0,M1,S3..4,B3..3,NM"];
+    let mut grid = Grid::new((120, 90), rules[0].parse().unwrap(), Topology::Torus);
+    Soup::new(17).fill(&mut grid, (120, 90));
+    for rule in &rules {
+        grid.set_rule(rule.parse().unwrap());
+        let ltl = grid.rule.larger_than_life().unwrap();
+        let mut board = Board::from_grid(&grid);
+        for generation in 1..=3 {
+            grid.run_lifecycle();
+            board = step(&board, &ltl, 2, Topology::Torus);
+            assert_eq!(Board::from_grid(&grid), board, "generation {} of {}", generation, rule);
+        }
+    }
+}
+
+#[test]
+fn other_engines_reject_larger_than_life_rules() {
+    let rule = "R5,C0,M1,S34..58,B34..45,NM".parse::<Rule>().unwrap();
+    assert!(HashLife::new(rule.clone()).is_err());
+    assert!(SparseGrid::new(rule).is_err());
+}
+
+fn neighbourhood() -> impl Strategy<Value = Neighbourhood> {
+    prop_oneof![
+        Just(Neighbourhood::Moore),
+        Just(Neighbourhood::VonNeumann),
+        Just(Neighbourhood::Circular),
+        Just(Neighbourhood::Hexagonal),
+    ]
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn counts_match_reference(width in 1..90_u32,
+                              height in 1..70_u32,
+                              topology in topology(),
+                              radius in 1..8_u32,
+                              middle in any::<bool>(),
+                              neighbourhood in neighbourhood(),
+                              survival in (0..40_u32, 0..40_u32),
+                              birth in (1..40_u32, 0..40_u32),
+                              states in 2..5_u16,
+                              seed in any::<u64>(),
+                              density in 0.0..1.0_f64,
+                              generations in 1..4_usize) {
+        // Ranges given as start and length
+        let rule = LargerThanLife {
+            radius,
+            middle,
+            survival : (survival.0, survival.0 + survival.1),
+            birth : (birth.0, birth.0 + birth.1),
+            neighbourhood,
+        };
+        let mut grid = Grid::new((width, height), rule.rule(states), topology);
+        let soup = Soup { density, ..Soup::new(seed) };
+        soup.fill(&mut grid, (width as usize, height as usize));
+
+        let mut board = Board::from_grid(&grid);
+        for _ in 0..generations {
+            grid.run_lifecycle();
+            board = step(&board, &rule, states, topology);
+            prop_assert_eq!(Board::from_grid(&grid), board.clone());
+        }
+    }
+}
@@ -0,0 +1,129 @@
+//! Rules on the von Neumann and hexagonal neighbourhoods: parsing, the
+//! cells each of them counts and a cross-check against a naive stepper.
+
+mod common;
+
+use proptest::prelude::*;
+
+use common::{alive, count_alive, topology, Board, MOORE};
+use rusty_life::engine::Engine;
+use rusty_life::grid::{Grid, Topology};
+use rusty_life::hashlife::HashLife;
+use rusty_life::rule::{Neighbourhood, Rule};
+use rusty_life::soup::Soup;
+use rusty_life::sparse::SparseGrid;
+
+/// Positions of the neighbours relative to the cell.
+fn offsets(neighbourhood : Neighbourhood) -> Vec<(isize, isize)> {
+    match neighbourhood {
+        Neighbourhood::VonNeumann => vec![(0, -1), (-1, 0), (1, 0), (0, 1)],
+        // Up and right and down and left are left out
+        Neighbourhood::Hexagonal => vec![(-1, -1), (0, -1), (-1, 0), (1, 0), (0, 1), (1, 1)],
+        _ => MOORE.to_vec(),
+    }
+}
+
+#[test]
+fn parses_neighbourhood_suffixes() {
+    let rule = "B2/S013V".parse::<Rule>().unwrap();
+    assert_eq!(rule.neighbourhood(), Neighbourhood::VonNeumann);
+    assert_eq!(rule.to_string(), "B2/S013V");
+    let rule = "B2/S34H".parse::<Rule>().unwrap();
+    assert_eq!(rule.neighbourhood(), Neighbourhood::Hexagonal);
+    assert_eq!(rule.to_string(), "B2/S34H");
+    assert_eq!("34/2h".parse::<Rule>().as_ref(), Ok(&rule));
+    assert_eq!("B2/S34/C5H".parse::<Rule>().unwrap().to_string(), "B2/S34/C5H");
+    assert_eq!(Rule::conway().neighbourhood(), Neighbourhood::Moore);
+    assert_ne!("B2/S34".parse::<Rule>(), Ok(rule));
+
+    assert!("B2/S015V".parse::<Rule>().is_err());
+    assert!("B27/S34H".parse::<Rule>().is_err());
+    assert!("B2a/S1V".parse::<Rule>().is_err());
+}
+
+#[test]
+fn single_cell_grows_into_neighbourhood() {
+    for rule in ["B1/S", "B1/SV", "B1/SH"] {
+        let rule = rule.parse::<Rule>().unwrap();
+        let mut grid = Grid::new((9, 9), rule.clone(), Topology::Plane);
+        grid.set_cell(4, 4, true);
+        grid.run_lifecycle();
+        let mut expected = offsets(rule.neighbourhood()).iter()
+                                                        .map(|(dx, dy)| ((4 + dx) as usize, (4 + dy) as usize))
+                                                        .collect::<Vec<_>>();
+        expected.sort_by_key(|(col, row)| (*row, *col));
+        assert_eq!(alive(&grid), expected, "{}", rule);
+    }
+}
+
+#[test]
+fn engines_agree() {
+    for rule in ["B2/S013V", "B2/S34H", "B24/S35H"] {
+        let rule = rule.parse::<Rule>().unwrap();
+        let size = 160;
+        let mut grid = Grid::new((size, size), rule.clone(), Topology::Plane);
+        let mut hashlife = HashLife::new(rule.clone()).unwrap();
+        let mut sparse = SparseGrid::new(rule.clone()).unwrap();
+        let mut soup = Grid::new((size, size), rule.clone(), Topology::Plane);
+        Soup::new(23).fill(&mut soup, (30, 30));
+        for (col, row) in alive(&soup) {
+            let (col, row) = (col as i64 + 65, row as i64 + 65);
+            Engine::set_cell(&mut grid, col, row, true);
+            Engine::set_cell(&mut hashlife, col, row, true);
+            Engine::set_cell(&mut sparse, col, row, true);
+        }
+        // Few enough generations that nothing reaches the border of the grid
+        for _ in 0..16 {
+            grid.run_lifecycle();
+            Engine::step(&mut hashlife, 0).unwrap();
+            Engine::step(&mut sparse, 0).unwrap();
+        }
+        assert_eq!(hashlife.population(), grid.population(), "{}", rule);
+        assert_eq!(sparse.population(), grid.population(), "{}", rule);
+        for (col, row) in alive(&grid) {
+            assert!(hashlife.get_cell(col as i64, row as i64) && sparse.get_cell(col as i64, row as i64));
+        }
+    }
+}
+
+/// Next generation counting the neighbours at `offsets`.
+fn step(board : &Board, rule : (u16, u16), offsets : &[(isize, isize)], topology : Topology) -> Board {
+    board.step(topology, |state, at| {
+        let mask = if state == 1 { rule.1 } else { rule.0 };
+        ((mask >> count_alive(at, offsets)) & 1) as u8
+    })
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn kernel_matches_reference(width in 1..200_u32,
+                                height in 1..150_u32,
+                                topology in topology(),
+                                hexagonal in any::<bool>(),
+                                birth in 0..0x80_u16,
+                                survival in 0..0x80_u16,
+                                seed in any::<u64>(),
+                                density in 0.0..1.0_f64,
+                                generations in 1..5_usize) {
+        let (neighbourhood, suffix, max) = if hexagonal {
+            (Neighbourhood::Hexagonal, "H", 6)
+        } else {
+            (Neighbourhood::VonNeumann, "V", 4)
+        };
+        let (birth, survival) = (birth & ((2 << max) - 1), survival & ((2 << max) - 1));
+        let digits = |mask : u16| (0..=max).filter(|n| mask & (1 << n) != 0).map(|n| n.to_string()).collect::<String>();
+        let rule = format!("B{}/S{}{}", digits(birth), digits(survival), suffix).parse::<Rule>().unwrap();
+        let mut grid = Grid::new((width, height), rule, topology);
+        let soup = Soup { density, ..Soup::new(seed) };
+        soup.fill(&mut grid, (width as usize, height as usize));
+
+        let mut board = Board::from_grid(&grid);
+        for _ in 0..generations {
+            grid.run_lifecycle();
+            board = step(&board, (birth, survival), &offsets(neighbourhood), topology);
+            prop_assert_eq!(Board::from_grid(&grid), board.clone());
+        }
+    }
+}
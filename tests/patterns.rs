@@ -0,0 +1,209 @@
+//! Known oscillators, spaceships and guns evolve as documented on every
+//! engine.
+
+use rusty_life::engine::{Engine, MAX_ITERATED_STEP_EXPONENT, MAX_STEP_EXPONENT};
+use rusty_life::file_reader::parse_rle;
+use rusty_life::grid::{Grid, Topology};
+use rusty_life::hashlife::HashLife;
+use rusty_life::rule::Rule;
+use rusty_life::sparse::SparseGrid;
+
+const BLINKER : &str = "x = 3, y = 1\n3o!";
+const GLIDER : &str = "x = 3, y = 3\nbo$2bo$3o!";
+const LWSS : &str = "x = 5, y = 4\nbo2bo$o4b$o3bo$4o!";
+const PULSAR : &str = "x = 13, y = 13\n\
+    2b3o3b3o2$o4bobo4bo$o4bobo4bo$o4bobo4bo$2b3o3b3o2$\
+    2b3o3b3o$o4bobo4bo$o4bobo4bo$o4bobo4bo2$2b3o3b3o!";
+const GOSPER_GUN : &str = "x = 36, y = 9\n\
+    24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o$\
+    2o8bo3bob2o4bobo$10bo5bo7bo$11bo3bo$12b2o!";
+
+/// Where patterns are placed, far enough from the border of the bounded
+/// boards for everything the tests look at.
+const ORIGIN : (i64, i64) = (100, 100);
+
+/// The engines under test: bounded boards of every topology and the
+/// unbounded ones.
+fn engines() -> Vec<(String, Box<dyn Engine>)> {
+    let mut engines : Vec<(String, Box<dyn Engine>)> = Vec::new();
+    for &topology in &[Topology::Torus, Topology::Plane, Topology::KleinBottle, Topology::CrossSurface] {
+        engines.push((format!("grid ({})", topology), Box::new(Grid::new((300, 250), Rule::conway(), topology))));
+    }
+    engines.push(("hashlife".to_string(), Box::new(HashLife::new(Rule::conway()).unwrap())));
+    engines.push(("sparse".to_string(), Box::new(SparseGrid::new(Rule::conway()).unwrap())));
+    engines
+}
+
+/// Cells of the RLE pattern placed at `ORIGIN`, sorted by row then column.
+fn cells(rle : &str) -> Vec<(i64, i64)> {
+    let pattern = parse_rle(rle.as_bytes()).unwrap();
+    let mut cells = pattern.pattern
+                           .iter()
+                           .map(|&(col, row)| (ORIGIN.1 + row as i64, ORIGIN.0 + col as i64))
+                           .collect::<Vec<_>>();
+    cells.sort_unstable();
+    cells
+}
+
+/// Alive cells of the engine, sorted by row then column.
+fn alive(engine : &dyn Engine) -> Vec<(i64, i64)> {
+    let mut cells = Vec::new();
+    if let Some(area) = engine.bounding_box() {
+        for row in area.row..area.row + area.height {
+            for col in area.col..area.col + area.width {
+                if engine.get_cell(col, row) {
+                    cells.push((row, col));
+                }
+            }
+        }
+    }
+    cells
+}
+
+fn shifted(cells : &[(i64, i64)], cols : i64, rows : i64) -> Vec<(i64, i64)> {
+    cells.iter().map(|&(row, col)| (row + rows, col + cols)).collect()
+}
+
+/// Places the pattern on every engine and hands it to `check`.
+fn for_each_engine<F>(rle : &str, check : F)
+    where F: Fn(&str, &mut dyn Engine, &[(i64, i64)]) {
+    let start = cells(rle);
+    for (name, mut engine) in engines() {
+        for &(row, col) in &start {
+            engine.set_cell(col, row, true);
+        }
+        check(&name, engine.as_mut(), &start);
+    }
+}
+
+/// Checks that the pattern comes back shifted by (`cols`, `rows`) after
+/// `period` generations, and not before if it is an oscillator.
+fn assert_period(rle : &str, period : u32, cols : i64, rows : i64) {
+    for_each_engine(rle, |name, engine, start| {
+        for generation in 1..=period {
+            engine.step(0).unwrap();
+            let cells = alive(engine);
+            if generation < period && cols == 0 && rows == 0 {
+                assert_ne!(cells, start, "{} repeats after {} generations on {}", rle, generation, name);
+            }
+        }
+        assert_eq!(alive(engine), shifted(start, cols, rows), "{} after {} generations on {}", rle, period, name);
+    });
+}
+
+#[test]
+fn blinker() {
+    assert_period(BLINKER, 2, 0, 0);
+}
+
+#[test]
+fn glider() {
+    assert_period(GLIDER, 4, 1, 1);
+}
+
+#[test]
+fn lwss() {
+    assert_period(LWSS, 4, -2, 0);
+}
+
+#[test]
+fn pulsar() {
+    assert_period(PULSAR, 3, 0, 0);
+}
+
+#[test]
+fn gosper_gun() {
+    for_each_engine(GOSPER_GUN, |name, engine, start| {
+        let in_gun = |&(row, col) : &(i64, i64)| {
+            row >= ORIGIN.1 && row < ORIGIN.1 + 9 && col >= ORIGIN.0 && col < ORIGIN.0 + 36
+        };
+        for cycle in 1..=4 {
+            for _ in 0..30 {
+                engine.step(0).unwrap();
+            }
+            let cells = alive(engine);
+            // The gun itself has period 30 and emits a glider of 5 cells
+            // every cycle
+            let gun = cells.iter().copied().filter(in_gun).collect::<Vec<_>>();
+            assert_eq!(gun, start, "the gun changed after {} cycles on {}", cycle, name);
+            assert_eq!(cells.len(), start.len() + 5 * cycle, "population after {} cycles on {}", cycle, name);
+        }
+    });
+}
+
+#[test]
+fn largest_step() {
+    let mut engine = HashLife::new(Rule::conway()).unwrap();
+    for &(row, col) in &cells(GLIDER) {
+        engine.set_cell(col, row, true);
+    }
+    assert!(engine.step(MAX_STEP_EXPONENT + 1).is_err());
+    assert_eq!(alive(&engine), cells(GLIDER));
+    // The glider moves a cell diagonally every 4 generations
+    engine.step(MAX_STEP_EXPONENT).unwrap();
+    let shift = 1_i64 << (MAX_STEP_EXPONENT - 2);
+    assert_eq!(alive(&engine), shifted(&cells(GLIDER), shift, shift));
+}
+
+#[test]
+fn largest_iterated_step() {
+    for (name, mut engine) in engines().into_iter().filter(|(name, _)| name != "hashlife") {
+        for &(row, col) in &cells(BLINKER) {
+            engine.set_cell(col, row, true);
+        }
+        assert_eq!(engine.max_step_exponent(), MAX_ITERATED_STEP_EXPONENT, "{}", name);
+        assert!(engine.step(MAX_ITERATED_STEP_EXPONENT + 1).is_err(), "{}", name);
+        assert_eq!(alive(engine.as_ref()), cells(BLINKER), "{}", name);
+    }
+}
+
+#[test]
+fn edge_of_the_hashlife_universe() {
+    let mut engine = HashLife::new(Rule::conway()).unwrap();
+    // Cells too far apart for a single universe are ignored
+    engine.set_cell(0, 0, true);
+    engine.set_cell(i64::MAX, i64::MIN, true);
+    engine.set_cell(i64::MIN, 0, true);
+    assert_eq!(engine.population(), 1);
+    assert!(!engine.get_cell(i64::MAX, i64::MIN));
+
+    // Blocks far apart fit, but don't leave room for a step
+    let far = 1_i64 << 60;
+    for &(col, row) in &[(0, 0), (1, 0), (0, 1), (1, 1)] {
+        engine.set_cell(col + far, row + far, true);
+    }
+    let hash = engine.state_hash();
+    assert!(engine.step(MAX_STEP_EXPONENT).is_err());
+    assert_eq!(engine.population(), 5);
+    assert_eq!(engine.state_hash(), hash);
+    assert!(engine.get_cell(far + 1, far + 1));
+}
+
+#[test]
+fn hashlife_keeps_results_per_step_size() {
+    let mut hashlife = HashLife::new(Rule::conway()).unwrap();
+    let mut sparse = SparseGrid::new(Rule::conway()).unwrap();
+    for &(row, col) in &cells(GOSPER_GUN) {
+        hashlife.set_cell(col, row, true);
+        sparse.set_cell(col, row, true);
+    }
+    // Alternating step sizes as when advancing by a number of generations
+    // that isn't a multiple of the step size
+    for &exponent in &[4, 2, 4, 0, 4, 2, 4] {
+        hashlife.step(exponent).unwrap();
+        sparse.step(exponent).unwrap();
+        assert_eq!(alive(&hashlife), alive(&sparse), "after a step of 2^{}", exponent);
+    }
+    assert!(hashlife.other_result_count() > 0);
+
+    // The other results survive garbage collection as long as their nodes
+    // are reachable, and are dropped with the rule
+    hashlife.collect_garbage();
+    for &exponent in &[2, 4, 0] {
+        hashlife.step(exponent).unwrap();
+        sparse.step(exponent).unwrap();
+        assert_eq!(alive(&hashlife), alive(&sparse), "after a step of 2^{}", exponent);
+    }
+    Engine::set_rule(&mut hashlife, "B36/S23".parse().unwrap()).unwrap();
+    assert_eq!(hashlife.other_result_count(), 0);
+}
@@ -0,0 +1,64 @@
+//! Cross-checks the bit packed kernel against a naive stepper that counts
+//! the neighbours of every cell one by one.
+
+mod common;
+
+use proptest::prelude::*;
+
+use common::{count_alive, topology, Board, MOORE};
+use rusty_life::grid::{Grid, Topology};
+use rusty_life::rule::Rule;
+use rusty_life::soup::Soup;
+
+/// Next generation under the rule with the given birth and survival
+/// masks, bit `n` standing for `n` alive neighbours.
+fn step(board : &Board, birth : u16, survival : u16, topology : Topology) -> Board {
+    board.step(topology, |state, at| {
+        let mask = if state == 1 { survival } else { birth };
+        ((mask >> count_alive(at, &MOORE)) & 1) as u8
+    })
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn kernel_matches_reference(width in 1..200_u32,
+                                height in 1..150_u32,
+                                topology in topology(),
+                                birth in 0..0x200_u16,
+                                survival in 0..0x200_u16,
+                                seed in any::<u64>(),
+                                density in 0.0..1.0_f64,
+                                generations in 1..5_usize) {
+        let mut grid = Grid::new((width, height), Rule::new(birth, survival), topology);
+        let soup = Soup { density, ..Soup::new(seed) };
+        soup.fill(&mut grid, (width as usize, height as usize));
+
+        let mut board = Board::from_grid(&grid);
+        for _ in 0..generations {
+            grid.run_lifecycle();
+            board = step(&board, birth, survival, topology);
+            prop_assert_eq!(Board::from_grid(&grid), board.clone());
+        }
+    }
+
+    #[test]
+    fn conway_matches_reference(width in 1..200_u32,
+                                height in 1..150_u32,
+                                topology in topology(),
+                                seed in any::<u64>(),
+                                generations in 1..20_usize) {
+        // Life keeps patterns alive for longer than random rules, which
+        // exercises the tracking of changed tiles
+        let mut grid = Grid::new((width, height), Rule::conway(), topology);
+        Soup::new(seed).fill(&mut grid, (width as usize, height as usize));
+
+        let mut board = Board::from_grid(&grid);
+        for _ in 0..generations {
+            grid.run_lifecycle();
+            board = step(&board, 1 << 3, (1 << 2) | (1 << 3), topology);
+            prop_assert_eq!(Board::from_grid(&grid), board.clone());
+        }
+    }
+}
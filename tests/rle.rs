@@ -0,0 +1,203 @@
+//! Reading and writing RLE files: every read error is reported with the
+//! position it was found at, written patterns are compact and read back
+//! unchanged.
+
+mod common;
+
+use common::alive;
+use rusty_life::engine::Rect;
+use rusty_life::file_reader::{parse_rle, read_rle, RleError};
+use rusty_life::file_writer::{write_rle, RleComments};
+use rusty_life::grid::{Grid, Topology};
+use rusty_life::rule::Rule;
+use rusty_life::soup::Soup;
+use rusty_life::{Config, Simulation};
+
+fn parse_err(rle : &str) -> RleError {
+    match parse_rle(rle.as_bytes()) {
+        Ok(_) => panic!("{:?} parsed", rle),
+        Err(err) => err,
+    }
+}
+
+#[test]
+fn reports_missing_header() {
+    match parse_err("") {
+        RleError::BadHeader { line : 1, column : 1, message } => assert!(message.contains("missing header"), "{}", message),
+        err => panic!("{:?}", err),
+    }
+    // Comments may come first, but the pattern may not
+    match parse_err("#N Glider\n#C A comment\n  bo$2bo$3o!") {
+        RleError::BadHeader { line : 3, column : 3, message } => assert!(message.contains("expected header"), "{}", message),
+        err => panic!("{:?}", err),
+    }
+}
+
+#[test]
+fn reports_bad_header_fields() {
+    match parse_err("x = 3\n3o!") {
+        RleError::BadHeader { line : 1, column : 1, message } => assert!(message.contains("\"y = \""), "{}", message),
+        err => panic!("{:?}", err),
+    }
+    match parse_err("x = 3, y = three\n3o!") {
+        RleError::BadHeader { line : 1, column : 12, message } => assert!(message.contains("invalid height"), "{}", message),
+        err => panic!("{:?}", err),
+    }
+    match parse_err("x = -3, y = 1\n3o!") {
+        RleError::BadHeader { line : 1, column : 5, message } => assert!(message.contains("invalid width"), "{}", message),
+        err => panic!("{:?}", err),
+    }
+    match parse_err("x = 3, y = 1, z = 2\n3o!") {
+        RleError::BadHeader { line : 1, column : 15, message } => assert!(message.contains("unknown field"), "{}", message),
+        err => panic!("{:?}", err),
+    }
+    match parse_err("x = 3, y = 1, rule = B9/S23\n3o!") {
+        RleError::BadHeader { line : 1, column : 22, .. } => (),
+        err => panic!("{:?}", err),
+    }
+}
+
+#[test]
+fn reports_unexpected_characters() {
+    match parse_err("x = 3, y = 2\nbo$\n2oz!") {
+        RleError::UnexpectedChar { line : 3, column : 3, found : 'z' } => (),
+        err => panic!("{:?}", err),
+    }
+    // A multi-state prefix needs a letter after it
+    match parse_err("x = 3, y = 1\n2p.!") {
+        RleError::UnexpectedChar { line : 2, column : 2, found : 'p' } => (),
+        err => panic!("{:?}", err),
+    }
+}
+
+#[test]
+fn reports_pattern_beyond_declared_size() {
+    match parse_err("x = 3, y = 2\nbo$\n2b2o!") {
+        RleError::PatternExceedsDeclaredSize { line : 3, column : 4, width : 3, height : 2 } => (),
+        err => panic!("{:?}", err),
+    }
+    match parse_err("x = 3, y = 2\n3o2$o!") {
+        RleError::PatternExceedsDeclaredSize { line : 2, column : 5, width : 3, height : 2 } => (),
+        err => panic!("{:?}", err),
+    }
+}
+
+#[test]
+fn reports_bad_run_counts() {
+    match parse_err("x = 3, y = 1\nb0o!") {
+        RleError::BadRunCount { line : 2, column : 2, count } => assert_eq!(count, "0"),
+        err => panic!("{:?}", err),
+    }
+    // The run count starts on the line before the tag it belongs to
+    match parse_err("x = 3, y = 1\nb00\no!") {
+        RleError::BadRunCount { line : 2, column : 2, count } => assert_eq!(count, "00"),
+        err => panic!("{:?}", err),
+    }
+    match parse_err("x = 3, y = 1\n99999999999999999999999o!") {
+        RleError::BadRunCount { line : 2, column : 1, .. } => (),
+        err => panic!("{:?}", err),
+    }
+}
+
+#[test]
+fn reports_missing_files() {
+    match read_rle("no/such/pattern.rle") {
+        Err(RleError::Io(err)) => assert_eq!(err.kind(), std::io::ErrorKind::NotFound),
+        Err(err) => panic!("{:?}", err),
+        Ok(_) => panic!("a missing file was read"),
+    }
+}
+
+#[test]
+fn errors_show_their_position() {
+    let err = parse_err("x = 3, y = 1\noz!");
+    assert_eq!(err.to_string(), "line 2, column 2: unexpected character 'z'");
+}
+
+/// RLE of `region` of a Conway grid with the given alive cells.
+fn write(size : (u32, u32), cells : &[(usize, usize)], region : Rect, comments : &RleComments) -> String {
+    let mut grid = Grid::new(size, Rule::conway(), Topology::Plane);
+    for &(col, row) in cells {
+        grid.set_cell(col, row, true);
+    }
+    let mut rle = Vec::new();
+    write_rle(&grid, region, comments, &mut rle).unwrap();
+    String::from_utf8(rle).unwrap()
+}
+
+#[test]
+fn writes_compact_rows() {
+    let region = Rect { col : 0, row : 0, width : 6, height : 8 };
+    // Empty rows collapse into a single line end with a count, dead cells
+    // at the end of a row and empty rows at the end are dropped
+    let rle = write((6, 8), &[(0, 0), (2, 0), (1, 4), (2, 4), (5, 5)], region, &RleComments::default());
+    assert_eq!(rle, "x = 6, y = 8, rule = B3/S23\nobo4$b2o$5bo!\n");
+
+    let rle = write((6, 8), &[], region, &RleComments::default());
+    assert_eq!(rle, "x = 6, y = 8, rule = B3/S23\n!\n");
+}
+
+#[test]
+fn writes_comments() {
+    let comments = RleComments {
+        name : "Blinker".to_string(),
+        author : "John Conway".to_string(),
+        comments : vec!["Period 2".to_string(), "Found in 1969".to_string()],
+    };
+    let rle = write((3, 1), &[(0, 0), (1, 0), (2, 0)], Rect { col : 0, row : 0, width : 3, height : 1 }, &comments);
+    assert_eq!(rle, "#N Blinker\n#O John Conway\n#C Period 2\n#C Found in 1969\n\
+                     x = 3, y = 1, rule = B3/S23\n3o!\n");
+
+    let pattern = parse_rle(rle.as_bytes()).unwrap();
+    assert_eq!(pattern.name, comments.name);
+    assert_eq!(pattern.author, comments.author);
+    assert_eq!(pattern.comments, comments.comments);
+}
+
+#[test]
+fn wraps_long_lines() {
+    // Runs of two alive cells and a dead one, 200 of them
+    let cells = (0..600).filter(|col| col % 3 != 2).map(|col| (col, 0)).collect::<Vec<_>>();
+    let rle = write((600, 1), &cells, Rect { col : 0, row : 0, width : 600, height : 1 }, &RleComments::default());
+    let lines = rle.lines().skip(1).collect::<Vec<_>>();
+    assert!(lines.len() > 1);
+    for line in &lines {
+        assert!(line.len() <= 70, "{:?} is too long", line);
+        // A run count is never split from its tag
+        assert!(!line.ends_with(|c : char| c.is_ascii_digit()), "{:?}", line);
+    }
+    assert_eq!(lines.concat(), format!("{}2o!", "2ob".repeat(199)));
+}
+
+#[test]
+fn soup_round_trip() {
+    let mut grid = Grid::new((150, 90), Rule::conway(), Topology::Torus);
+    Soup { density : 0.3, ..Soup::new(7) }.fill(&mut grid, (150, 90));
+    let region = Rect { col : 0, row : 0, width : 150, height : 90 };
+    let mut rle = Vec::new();
+    write_rle(&grid, region, &RleComments::default(), &mut rle).unwrap();
+    assert!(rle.split(|&b| b == b'\n').all(|line| line.len() <= 70));
+
+    let pattern = parse_rle(rle.as_slice()).unwrap();
+    assert_eq!((pattern.width, pattern.height), (150, 90));
+    assert_eq!(pattern.rule, Some(Rule::conway()));
+    assert_eq!(pattern.pattern, alive(&grid));
+}
+
+#[test]
+fn loading_switches_to_the_pattern_rule() {
+    let filepath = std::env::temp_dir().join(format!("rusty-life-{}-highlife.rle", std::process::id()));
+    std::fs::write(&filepath, "x = 3, y = 1, rule = B36/S23\n3o!\n").unwrap();
+    let config = Config { board_size : (20, 20), pattern : Some(filepath.clone()), pattern_offset : (5, 5), ..Config::default() };
+    let sim = Simulation::new(&config);
+    let highlife = "B36/S23".parse::<Rule>().unwrap();
+    let same_rule = Simulation::new(&Config { rule : highlife.clone(), ..config });
+    std::fs::remove_file(&filepath).unwrap();
+
+    let sim = sim.unwrap();
+    assert_eq!(sim.rule(), highlife);
+    assert_eq!(sim.replaced_rule(), Some(&Rule::conway()));
+    assert_eq!(sim.engine().population(), 3);
+    assert!(sim.engine().get_cell(7, 5));
+    assert_eq!(same_rule.unwrap().replaced_rule(), None);
+}
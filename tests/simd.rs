@@ -0,0 +1,68 @@
+//! Every SIMD backend the CPU supports steps boards exactly like the
+//! scalar kernel.
+
+use rusty_life::grid::{Grid, Topology};
+use rusty_life::rule::Rule;
+use rusty_life::simd::Backend;
+use rusty_life::soup::Soup;
+
+const TOPOLOGIES : [Topology; 4] = [Topology::Torus, Topology::Plane, Topology::KleinBottle, Topology::CrossSurface];
+
+fn soup_grid(size : (u32, u32), rule : &str, topology : Topology, backend : Backend, seed : u64) -> Grid {
+    let mut grid = Grid::new(size, rule.parse().unwrap(), topology);
+    grid.set_backend(backend).unwrap();
+    let soup = Soup { density : 0.4, ..Soup::new(seed) };
+    soup.fill(&mut grid, (size.0 as usize, size.1 as usize));
+    grid
+}
+
+fn assert_matches_scalar(size : (u32, u32), rule : &str, generations : usize) {
+    for backend in Backend::ALL.iter().copied().filter(|b| b.is_supported()) {
+        for (seed, &topology) in TOPOLOGIES.iter().enumerate() {
+            let mut scalar = soup_grid(size, rule, topology, Backend::Scalar, seed as u64);
+            let mut simd = soup_grid(size, rule, topology, backend, seed as u64);
+            for generation in 1..=generations {
+                scalar.run_lifecycle();
+                simd.run_lifecycle();
+                assert!(scalar.cells() == simd.cells(),
+                        "{} differs on a {:?} {:?} board in generation {} of {}",
+                        backend, size, topology, generation, rule);
+            }
+        }
+    }
+}
+
+#[test]
+fn detects_a_supported_backend() {
+    assert!(Backend::detect().is_supported());
+    assert_eq!(Grid::new((64, 64), Rule::conway(), Topology::Torus).backend(), Backend::detect());
+}
+
+#[test]
+fn rejects_unsupported_backends() {
+    let mut grid = Grid::new((64, 64), Rule::conway(), Topology::Torus);
+    for backend in Backend::ALL.iter().copied() {
+        assert_eq!(grid.set_backend(backend).is_ok(), backend.is_supported());
+    }
+}
+
+#[test]
+fn conway_on_wide_boards() {
+    // Rows of 20 words exercise full vectors and a scalar remainder
+    assert_matches_scalar((1280, 150), "B3/S23", 30);
+    assert_matches_scalar((1250, 70), "B3/S23", 30);
+}
+
+#[test]
+fn narrow_boards() {
+    for &size in &[(64, 64), (130, 3), (200, 1), (1, 130), (700, 65)] {
+        assert_matches_scalar(size, "B3/S23", 10);
+    }
+}
+
+#[test]
+fn other_rules() {
+    for rule in &["B36/S23", "B2/S", "B3678/S34678", "B0123478/S01234678"] {
+        assert_matches_scalar((1000, 140), rule, 10);
+    }
+}
@@ -0,0 +1,131 @@
+//! Random soups: reproducible from their seed, exact in density and
+//! invariant under their symmetry.
+
+mod common;
+
+use proptest::prelude::*;
+
+use common::alive;
+use rusty_life::grid::{Grid, Region, Topology};
+use rusty_life::rule::Rule;
+use rusty_life::soup::{Soup, Symmetry};
+
+const SYMMETRIES : [Symmetry; 16] = [
+    Symmetry::C1, Symmetry::C2_1, Symmetry::C2_2, Symmetry::C2_4, Symmetry::C4_1, Symmetry::C4_4,
+    Symmetry::D2_P1, Symmetry::D2_P2, Symmetry::D2_X, Symmetry::D4_P1, Symmetry::D4_P2, Symmetry::D4_P4,
+    Symmetry::D4_X1, Symmetry::D4_X4, Symmetry::D8_1, Symmetry::D8_4,
+];
+
+type Transform = fn((usize, usize), usize, usize) -> (usize, usize);
+
+const ROTATE_90 : Transform = |(x, y), width, _| (width - 1 - y, x);
+const ROTATE_180 : Transform = |(x, y), width, height| (width - 1 - x, height - 1 - y);
+const FLIP_ROWS : Transform = |(x, y), _, height| (x, height - 1 - y);
+const FLIP_COLS : Transform = |(x, y), width, _| (width - 1 - x, y);
+const TRANSPOSE : Transform = |(x, y), _, _| (y, x);
+const ANTI_TRANSPOSE : Transform = |(x, y), width, height| (width - 1 - y, height - 1 - x);
+
+/// Transformations a soup with `symmetry` has to be invariant under.
+fn invariants(symmetry : Symmetry) -> Vec<Transform> {
+    match symmetry {
+        Symmetry::C1 => vec![],
+        Symmetry::C2_1 | Symmetry::C2_2 | Symmetry::C2_4 => vec![ROTATE_180],
+        Symmetry::C4_1 | Symmetry::C4_4 => vec![ROTATE_90],
+        Symmetry::D2_P1 | Symmetry::D2_P2 => vec![FLIP_ROWS],
+        Symmetry::D2_X => vec![TRANSPOSE],
+        Symmetry::D4_P1 | Symmetry::D4_P2 | Symmetry::D4_P4 => vec![FLIP_ROWS, FLIP_COLS],
+        Symmetry::D4_X1 | Symmetry::D4_X4 => vec![TRANSPOSE, ANTI_TRANSPOSE],
+        Symmetry::D8_1 | Symmetry::D8_4 => vec![ROTATE_90, FLIP_ROWS],
+    }
+}
+
+fn fill(soup : &Soup, board_size : (u32, u32)) -> Grid {
+    let mut grid = Grid::new(board_size, Rule::conway(), Topology::Plane);
+    soup.fill(&mut grid, (board_size.0 as usize, board_size.1 as usize));
+    grid
+}
+
+#[test]
+fn parses_and_prints_symmetries() {
+    for &symmetry in &SYMMETRIES {
+        assert_eq!(symmetry.to_string().parse::<Symmetry>(), Ok(symmetry));
+    }
+    assert_eq!("d4_+1".parse::<Symmetry>(), Ok(Symmetry::D4_P1));
+    assert!("D3_1".parse::<Symmetry>().is_err());
+}
+
+#[test]
+fn seeds_are_reproducible() {
+    let soup = Soup { density : 0.4, ..Soup::new(1234) };
+    let cells = alive(&fill(&soup, (64, 48)));
+    assert!(!cells.is_empty());
+    assert_eq!(alive(&fill(&soup, (64, 48))), cells);
+    // Filling overwrites whatever was in the soup's area before
+    let mut grid = fill(&Soup { density : 1.0, ..soup }, (64, 48));
+    soup.fill(&mut grid, (64, 48));
+    assert_eq!(alive(&grid), cells);
+
+    for seed in 0..20 {
+        assert_ne!(alive(&fill(&Soup { seed, ..soup }, (64, 48))), cells, "seed {}", seed);
+    }
+}
+
+#[test]
+fn asymmetric_density_is_exact() {
+    for &(density, expected) in &[(0.0, 0), (0.25, 768), (0.5, 1536), (0.333, 1023), (1.0, 3072), (1.5, 3072)] {
+        let soup = Soup { density, ..Soup::new(5) };
+        let grid = fill(&soup, (64, 48));
+        assert_eq!(grid.population(), expected, "density {}", density);
+        assert_eq!(soup.fill(&mut Grid::new((64, 48), Rule::conway(), Topology::Plane), (64, 48)), expected);
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(256))]
+
+    #[test]
+    fn soups_have_their_symmetry(symmetry in prop::sample::select(SYMMETRIES.to_vec()),
+                                 col in 0..10_usize,
+                                 row in 0..10_usize,
+                                 width in 1..40_usize,
+                                 height in 1..40_usize,
+                                 density in 0.0..1.0_f64,
+                                 seed in any::<u64>()) {
+        let region = Region { col, row, width, height };
+        let soup = Soup { seed, density, symmetry, region : Some(region) };
+        let grid = fill(&soup, (50, 50));
+
+        let area = symmetry.fit(region);
+        prop_assert!(area.col >= col && area.col + area.width <= col + width);
+        prop_assert!(area.row >= row && area.row + area.height <= row + height);
+        // The suffix is the position of the center: 1 on a cell, 2 on the
+        // edge between two cells and 4 on the corner between four
+        let (odd_width, odd_height) = (area.width % 2 == 1, area.height % 2 == 1);
+        match symmetry {
+            Symmetry::C2_1 | Symmetry::C4_1 | Symmetry::D4_P1 | Symmetry::D4_X1 | Symmetry::D8_1 =>
+                prop_assert!(odd_width && odd_height),
+            Symmetry::C2_2 | Symmetry::D4_P2 => prop_assert!(!odd_width && odd_height),
+            Symmetry::C2_4 | Symmetry::C4_4 | Symmetry::D4_P4 | Symmetry::D4_X4 | Symmetry::D8_4 =>
+                prop_assert!(!odd_width && !odd_height),
+            Symmetry::D2_P1 => prop_assert!(odd_height),
+            Symmetry::D2_P2 => prop_assert!(!odd_height),
+            Symmetry::C1 | Symmetry::D2_X => (),
+        }
+        // Rotations by quarter turns and diagonal mirrors need a square
+        if matches!(symmetry, Symmetry::C4_1 | Symmetry::C4_4 | Symmetry::D2_X | Symmetry::D4_X1 |
+                              Symmetry::D4_X4 | Symmetry::D8_1 | Symmetry::D8_4) {
+            prop_assert_eq!(area.width, area.height);
+        }
+
+        let cells = alive(&grid);
+        for &(col, row) in &cells {
+            prop_assert!(col >= area.col && col < area.col + area.width &&
+                         row >= area.row && row < area.row + area.height);
+            for transform in invariants(symmetry) {
+                let (x, y) = transform((col - area.col, row - area.row), area.width, area.height);
+                prop_assert!(grid.get_cell(area.col + x, area.row + y),
+                             "{} maps ({}, {}) to a dead cell", symmetry, col, row);
+            }
+        }
+    }
+}
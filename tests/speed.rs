@@ -0,0 +1,76 @@
+//! Pacing of the viewer and advancing by an exact number of generations.
+
+use std::time::{Duration, Instant};
+
+use rusty_life::engine::EngineKind;
+use rusty_life::speed::{Pacer, Speed};
+use rusty_life::{Config, Simulation};
+
+#[test]
+fn parses_and_prints_speeds() {
+    for s in &["10ms", "0ms", "4/frame", "1000/s", "2.5/s"] {
+        assert_eq!(s.parse::<Speed>().unwrap().to_string(), *s);
+    }
+    for s in &["10", "0/frame", "0.5/s", "fast"] {
+        assert!(s.parse::<Speed>().is_err(), "{}", s);
+    }
+}
+
+#[test]
+fn delay_takes_one_step_once_it_passed() {
+    let start = Instant::now();
+    let mut pacer = Pacer::new(Speed::Delay(Duration::from_millis(10)), start);
+    assert_eq!(pacer.steps_due(start + Duration::from_millis(5), 1), 0);
+    assert_eq!(pacer.steps_due(start + Duration::from_millis(10), 1), 1);
+    assert_eq!(pacer.steps_due(start + Duration::from_millis(15), 1), 0);
+}
+
+#[test]
+fn steps_per_frame_ignore_time() {
+    let start = Instant::now();
+    let mut pacer = Pacer::new(Speed::StepsPerFrame(3000), start);
+    assert_eq!(pacer.steps_due(start, 8), 3000);
+    assert_eq!(pacer.steps_due(start + Duration::from_secs(1), 8), 3000);
+}
+
+#[test]
+fn gens_per_second_keep_up_the_rate_in_whole_steps() {
+    let start = Instant::now();
+    let mut pacer = Pacer::new(Speed::GensPerSecond(1000.0), start);
+    // 60 frames of a second with steps of 4 generations
+    let steps = (1..=60).map(|frame| pacer.steps_due(start + Duration::from_secs(frame) / 60, 4))
+                        .sum::<u64>();
+    assert_eq!(steps, 250);
+
+    // A long hang doesn't pile up more than a second of generations
+    assert_eq!(pacer.steps_due(start + Duration::from_secs(10), 4), 250);
+    pacer.reset(start + Duration::from_secs(20));
+    assert_eq!(pacer.steps_due(start + Duration::from_secs(20), 4), 0);
+}
+
+#[test]
+fn advance_matches_single_steps() {
+    for &engine in &[EngineKind::Grid, EngineKind::HashLife, EngineKind::Sparse] {
+        let config = Config { board_size : (200, 200), seed : Some(11), engine, ..Config::default() };
+        let mut advanced = Simulation::new(&config).unwrap();
+        let mut stepped = Simulation::new(&config).unwrap();
+        advanced.advance(77).unwrap();
+        for _ in 0..77 {
+            stepped.step().unwrap();
+        }
+        assert_eq!(advanced.generation(), 77);
+        assert_eq!(advanced.engine().state_hash(), stepped.engine().state_hash(), "{}", engine);
+
+        // Steps of 8 generations and the remaining 4 and 1, HashLife keeps
+        // the results of the small steps while it switches between them
+        advanced.set_step_exponent(3).unwrap();
+        for _ in 0..3 {
+            advanced.advance(77).unwrap();
+            for _ in 0..77 {
+                stepped.step().unwrap();
+            }
+            assert_eq!(advanced.engine().state_hash(), stepped.engine().state_hash(), "{}", engine);
+        }
+        assert_eq!(advanced.generation(), 4 * 77);
+    }
+}
@@ -0,0 +1,266 @@
+//! Rules given by Golly rule tables: parsing, symmetries, WireWorld,
+//! multi-state RLE and a cross-check against a naive stepper.
+
+mod common;
+
+use proptest::prelude::*;
+
+use common::{topology, Board};
+use rusty_life::engine::{Engine, Rect};
+use rusty_life::file_reader::{parse_rle, RleError};
+use rusty_life::file_writer::{write_rle, RleComments};
+use rusty_life::grid::{Grid, Topology};
+use rusty_life::hashlife::HashLife;
+use rusty_life::rule::{Neighbourhood, Rule};
+use rusty_life::sparse::SparseGrid;
+use rusty_life::table::RuleTable;
+
+const EMPTY : u8 = 0;
+const HEAD : u8 = 1;
+const TAIL : u8 = 2;
+const WIRE : u8 = 3;
+
+/// Two-state table born from a single neighbour to the north, with the
+/// given symmetries.
+fn north_birth(symmetries : &str) -> RuleTable {
+    format!("@RULE North\n@TABLE\nn_states:2\nneighborhood:Moore\nsymmetries:{}\n0,1,0,0,0,0,0,0,0,1\n",
+            symmetries).parse().unwrap()
+}
+
+/// Neighbours of a Moore cell with only the one at `position` in state 1.
+fn single(position : usize) -> [u8; 8] {
+    let mut neighbours = [0; 8];
+    neighbours[position] = 1;
+    neighbours
+}
+
+#[test]
+fn parses_wireworld() {
+    let rule = "WireWorld".parse::<Rule>().unwrap();
+    assert_eq!(rule.to_string(), "WireWorld");
+    assert_eq!(rule.states(), 4);
+    assert_eq!(rule.neighbourhood(), Neighbourhood::Moore);
+    assert_eq!("wireworld".parse::<Rule>().as_ref(), Ok(&rule));
+    assert_ne!(rule, Rule::conway());
+
+    let table = rule.rule_table().unwrap();
+    assert_eq!(table.name(), "WireWorld");
+    assert_eq!(table.color(EMPTY), Some([48, 48, 48]));
+    assert_eq!(table.color(WIRE), Some([255, 128, 0]));
+    assert_eq!(table.color(4), None);
+    assert!(Rule::conway().rule_table().is_none());
+
+    let mut neighbours = [EMPTY; 8];
+    assert_eq!(table.next_state(HEAD, &neighbours), TAIL);
+    assert_eq!(table.next_state(TAIL, &neighbours), WIRE);
+    assert_eq!(table.next_state(WIRE, &neighbours), WIRE);
+    assert_eq!(table.next_state(EMPTY, &single(3)), EMPTY);
+    for heads in 1..=8 {
+        neighbours[heads - 1] = HEAD;
+        let expected = if heads <= 2 { HEAD } else { WIRE };
+        assert_eq!(table.next_state(WIRE, &neighbours), expected, "{} heads", heads);
+    }
+}
+
+#[test]
+fn rejects_invalid_tables() {
+    let table = |body : &str| format!("@RULE Test\n@TABLE\n{}", body).parse::<RuleTable>();
+    assert!("@TABLE\nn_states:2\n".parse::<RuleTable>().is_err());
+    assert!(table("n_states:1\nneighborhood:Moore\n").is_err());
+    assert!(table("n_states:300\nneighborhood:Moore\n").is_err());
+    assert!(table("n_states:2\nneighborhood:Triangular\n").is_err());
+    assert!(table("n_states:2\nneighborhood:Moore\nsymmetries:rotate3\n0,1,0,0,0,0,0,0,0,1\n").is_err());
+    assert!(table("n_states:2\nneighborhood:Moore\nvar a={0,1}\n0,b,0,0,0,0,0,0,0,1\n").is_err());
+    assert!(table("n_states:2\nneighborhood:Moore\n0,1,0,0,0,0,0,0,1\n").is_err());
+    assert!(table("n_states:2\nneighborhood:vonNeumann\n0,1,0,0,2,1\n").is_err());
+    assert!(table("n_states:2\nneighborhood:Moore\nvar a={0,1}\n0,1,0,0,0,0,0,0,0,a\n").is_err());
+    assert!("@RULE Test\n@TREE\nnum_states=2\n".parse::<RuleTable>().is_err());
+    assert!("NoSuchRuleTable".parse::<Rule>().is_err());
+    assert!("no/such.rule".parse::<Rule>().is_err());
+
+    let err = table("n_states:2\nneighborhood:Moore\n0,1,0\n").unwrap_err();
+    assert!(err.contains("line 5"), "{}", err);
+}
+
+#[test]
+fn applies_symmetries() {
+    let none = north_birth("none");
+    assert_eq!(none.next_state(0, &single(0)), 1);
+    assert!((1..8).all(|position| none.next_state(0, &single(position)) == 0));
+
+    // Rotations by quarter turns move the north neighbour to the other
+    // orthogonal ones, never to a diagonal one
+    let rotate4 = north_birth("rotate4");
+    for position in 0..8 {
+        assert_eq!(rotate4.next_state(0, &single(position)), (position % 2 == 0) as u8, "rotate4 {}", position);
+    }
+    let permute = north_birth("permute");
+    assert!((0..8).all(|position| permute.next_state(0, &single(position)) == 1));
+    assert_eq!(permute.next_state(0, &[1, 1, 0, 0, 0, 0, 0, 0]), 0);
+    assert_eq!(permute.next_state(1, &single(2)), 1);
+
+    // Variables used twice take the same state at both positions
+    let bound = "@RULE Bound\n@TABLE\nn_states:3\nneighborhood:vonNeumann\nsymmetries:none\n\
+                 var a={1,2}\n0,a,0,a,0,1\n".parse::<RuleTable>().unwrap();
+    assert_eq!(bound.neighbourhood(), Neighbourhood::VonNeumann);
+    assert_eq!(bound.next_state(0, &[2, 0, 2, 0]), 1);
+    assert_eq!(bound.next_state(0, &[1, 0, 2, 0]), 0);
+}
+
+#[test]
+fn electron_runs_along_wire() {
+    let mut grid = Grid::new((16, 5), "WireWorld".parse().unwrap(), Topology::Plane);
+    for col in 1..15 {
+        grid.set_state(col, 2, WIRE);
+    }
+    grid.set_state(1, 2, TAIL);
+    grid.set_state(2, 2, HEAD);
+    assert_eq!(grid.population(), 14);
+
+    for generation in 1..=10 {
+        grid.run_lifecycle();
+        let wire = (0..16).map(|col| grid.get_state(col, 2)).collect::<Vec<_>>();
+        let mut expected = vec![EMPTY; 16];
+        expected[1..15].fill(WIRE);
+        expected[1 + generation] = TAIL;
+        expected[2 + generation] = HEAD;
+        assert_eq!(wire, expected, "generation {}", generation);
+        assert_eq!(grid.population(), 14);
+    }
+}
+
+#[test]
+fn other_engines_reject_tables() {
+    let rule = "WireWorld".parse::<Rule>().unwrap();
+    assert!(HashLife::new(rule.clone()).is_err());
+    assert!(SparseGrid::new(rule.clone()).is_err());
+    let mut hashlife = HashLife::new(Rule::conway()).unwrap();
+    assert!(Engine::set_rule(&mut hashlife, rule).is_err());
+    assert_eq!(Engine::rule(&hashlife), Rule::conway());
+}
+
+#[test]
+fn multi_state_rle_round_trip() {
+    let mut grid = Grid::new((40, 10), "WireWorld".parse().unwrap(), Topology::Torus);
+    let cells = [(3, 1, HEAD), (4, 1, TAIL), (5, 1, WIRE), (6, 1, WIRE), (30, 1, WIRE), (3, 7, WIRE)];
+    for &(col, row, state) in &cells {
+        grid.set_state(col, row, state);
+    }
+    let region = Engine::bounding_box(&grid).unwrap();
+    assert_eq!(region, Rect { col : 3, row : 1, width : 28, height : 7 });
+
+    let mut rle = Vec::new();
+    write_rle(&grid, region, &RleComments::default(), &mut rle).unwrap();
+    let rle = String::from_utf8(rle).unwrap();
+    assert_eq!(rle, "x = 28, y = 7, rule = WireWorld\nAB2C23.C6$C!\n");
+
+    let pattern = parse_rle(rle.as_bytes()).unwrap();
+    assert_eq!(pattern.rule.as_ref().map(|rule| rule.to_string()), Some("WireWorld".to_string()));
+    let read = pattern.pattern.iter().zip(&pattern.states)
+                              .map(|(&(col, row), &state)| (col + 3, row + 1, state))
+                              .collect::<Vec<_>>();
+    assert_eq!(read, cells.to_vec());
+
+    // States past 24 take a prefix before their letter
+    let pattern = parse_rle("x = 4, y = 1\n2pA.yO!".as_bytes()).unwrap();
+    assert_eq!(pattern.states, vec![25, 25, 255]);
+    assert!(parse_rle("x = 2, y = 1\nyP!".as_bytes()).is_err());
+}
+
+#[test]
+fn reads_rule_files() {
+    let filepath = std::env::temp_dir().join(format!("rusty-life-{}-Diagonal.rule", std::process::id()));
+    std::fs::write(&filepath, "@RULE Diagonal\n\
+                               Born with a single alive neighbour to the north west.\n\
+                               @TABLE\nn_states:2\nneighborhood:Moore\nsymmetries:none\n\
+                               0,0,0,0,0,0,0,0,1,1\n1,a,b,c,d,e,f,g,h,0\n\
+                               @COLORS\n200 0 0 0 0 200\n").unwrap();
+    let rule = filepath.to_str().unwrap().parse::<Rule>();
+    std::fs::remove_file(&filepath).unwrap();
+    // The variables a to h are never declared
+    assert!(rule.is_err());
+
+    std::fs::write(&filepath, "@RULE Diagonal\n@TABLE\nn_states:3\nneighborhood:Moore\nsymmetries:none\n\
+                               var a={0,1,2}\n0,0,0,0,0,0,0,0,1,1\n1,a,a,a,a,a,a,a,a,2\n\
+                               @COLORS\n200 0 0 0 0 200\n").unwrap();
+    let rule = filepath.to_str().unwrap().parse::<Rule>();
+    std::fs::remove_file(&filepath).unwrap();
+    let rule = rule.unwrap();
+    assert_eq!(rule.to_string(), "Diagonal");
+    let table = rule.rule_table().unwrap();
+    assert_eq!(table.color(1), Some([200, 0, 0]));
+    assert_eq!(table.color(2), Some([0, 0, 200]));
+    assert_eq!(table.next_state(0, &single(7)), 1);
+    assert_eq!(table.next_state(1, &[0; 8]), 2);
+    assert_eq!(table.next_state(1, &single(0)), 1);
+}
+
+#[test]
+fn patterns_only_name_rule_tables() {
+    let filepath = std::env::temp_dir().join(format!("rusty-life-{}-Header.rule", std::process::id()));
+    std::fs::write(&filepath, "@RULE Header\n@TABLE\nn_states:2\nneighborhood:Moore\n0,1,0,0,0,0,0,0,0,1\n").unwrap();
+    let path = filepath.to_str().unwrap().to_string();
+    let from_path = path.parse::<Rule>();
+    let from_header = parse_rle(format!("x = 1, y = 1, rule = {}\no!", path).as_bytes());
+    std::fs::remove_file(&filepath).unwrap();
+
+    // Given on the command line the path is read, given by a pattern it
+    // isn't
+    assert_eq!(from_path.unwrap().to_string(), "Header");
+    match from_header {
+        Err(RleError::BadHeader { line : 1, column : 22, message }) => assert!(message.contains("name"), "{}", message),
+        other => panic!("{:?}", other.map(|p| p.rule)),
+    }
+    assert!(Rule::from_pattern("rules/WireWorld.RULE").is_err());
+    assert_eq!(Rule::from_pattern("WireWorld").unwrap().states(), 4);
+    assert_eq!(Rule::from_pattern("B36/S23"), "B36/S23".parse());
+
+    // Names never reach outside of the current directory
+    for name in &["..\\WireWorld", "rules\\WireWorld", "Wire World.x", ""] {
+        assert!(RuleTable::find(name).is_err(), "{:?}", name);
+    }
+    assert_eq!(RuleTable::find("WIREWORLD").unwrap().name(), "WireWorld");
+}
+
+/// Next generation looking up each cell in the table on its own.
+fn step(board : &Board, table : &RuleTable, topology : Topology) -> Board {
+    board.step(topology, |state, at| {
+        let neighbours = table.offsets().iter().map(|(dx, dy)| at(*dx, *dy)).collect::<Vec<_>>();
+        table.next_state(state, &neighbours)
+    })
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn grid_matches_reference(width in 1..150_u32,
+                              height in 1..100_u32,
+                              topology in topology(),
+                              states in prop::collection::vec(0..4_u8, 64),
+                              seed in any::<u64>(),
+                              generations in 1..5_usize) {
+        let rule = "WireWorld".parse::<Rule>().unwrap();
+        let table = rule.rule_table().unwrap().clone();
+        let mut grid = Grid::new((width, height), rule, topology);
+        let (width, height) = (width as usize, height as usize);
+        // Mostly wire with the random states sprinkled in
+        let mut board = Board {
+            width,
+            height,
+            states : (0..width * height).map(|i| {
+                let hash = (i as u64 ^ seed).wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 58;
+                if hash < 40 { WIRE } else { states[hash as usize] }
+            }).collect(),
+        };
+        for (i, &state) in board.states.iter().enumerate() {
+            grid.set_state(i % width, i / width, state);
+        }
+        prop_assert_eq!(Board::from_grid(&grid), board.clone());
+        for _ in 0..generations {
+            grid.run_lifecycle();
+            board = step(&board, &table, topology);
+            prop_assert_eq!(Board::from_grid(&grid), board.clone());
+        }
+    }
+}
@@ -0,0 +1,74 @@
+//! Only tiles that changed in the previous generation and their
+//! neighbours are recomputed.
+
+mod common;
+
+use common::alive;
+use rusty_life::grid::{Grid, Topology};
+use rusty_life::rule::Rule;
+
+/// Block in the tile at (4, 4) of a board of 10 x 10 tiles.
+fn block(topology : Topology) -> Grid {
+    let mut grid = Grid::new((640, 640), Rule::conway(), topology);
+    for &(col, row) in &[(300, 300), (301, 300), (300, 301), (301, 301)] {
+        grid.set_cell(col, row, true);
+    }
+    grid
+}
+
+#[test]
+fn still_lifes_go_idle() {
+    let mut grid = block(Topology::Plane);
+    assert_eq!(grid.active_tiles(), 100);
+    grid.run_lifecycle();
+    assert_eq!(grid.active_tiles(), 0);
+    grid.run_lifecycle();
+    assert_eq!(grid.population(), 4);
+
+    // A cell set near the block wakes its tile and the neighbouring ones,
+    // the lone cell dies in the next generation and they go idle again
+    grid.set_cell(310, 310, true);
+    assert_eq!(grid.active_tiles(), 9);
+    grid.run_lifecycle();
+    assert_eq!(grid.active_tiles(), 9);
+    assert_eq!(grid.population(), 4);
+    grid.run_lifecycle();
+    assert_eq!(grid.active_tiles(), 0);
+
+    // Only the neighbours on the board for a corner tile
+    grid.set_cell(0, 0, true);
+    assert_eq!(grid.active_tiles(), 4);
+}
+
+#[test]
+fn glued_edges_wake_the_border() {
+    let mut grid = block(Topology::Torus);
+    grid.run_lifecycle();
+    assert_eq!(grid.active_tiles(), 0);
+    // The 36 tiles along the border and the one diagonally inside
+    grid.set_cell(0, 0, true);
+    assert_eq!(grid.active_tiles(), 37);
+}
+
+#[test]
+fn woken_tiles_evolve_like_fresh_ones() {
+    for &topology in &[Topology::Plane, Topology::Torus] {
+        let mut grid = block(topology);
+        grid.run_lifecycle();
+        assert_eq!(grid.active_tiles(), 0);
+        // The cell next to the block and the block evolve for a while
+        grid.set_cell(302, 299, true);
+
+        let start = alive(&grid);
+        let mut fresh = Grid::new((640, 640), Rule::conway(), topology);
+        for &(col, row) in &start {
+            fresh.set_cell(col, row, true);
+        }
+        for generation in 1..=50 {
+            grid.run_lifecycle();
+            fresh.run_lifecycle();
+            assert_eq!(alive(&grid), alive(&fresh), "generation {} on {}", generation, topology);
+        }
+        assert_ne!(alive(&grid), start);
+    }
+}
@@ -0,0 +1,95 @@
+//! The background worker applies commands in order and keeps publishing
+//! snapshots while it runs.
+
+use std::time::{Duration, Instant};
+
+use rusty_life::engine::{EngineKind, MAX_STEP_EXPONENT};
+use rusty_life::rule::Rule;
+use rusty_life::speed::Speed;
+use rusty_life::worker::{Command, Worker};
+use rusty_life::{Config, Simulation};
+
+fn simulation() -> Simulation {
+    Simulation::new(&Config { board_size : (100, 80), seed : Some(3), ..Config::default() }).unwrap()
+}
+
+#[test]
+fn applies_commands_in_order() {
+    let worker = Worker::spawn(simulation(), Speed::default(), false);
+    worker.send(Command::Advance(10));
+    worker.send(Command::SetStepExponent(2));
+    worker.send(Command::Step);
+    worker.send(Command::SetCell { col : 5, row : 6, value : true });
+    let sim = worker.stop();
+
+    let mut expected = simulation();
+    expected.advance(14).unwrap();
+    expected.engine_mut().set_cell(5, 6, true);
+    assert_eq!(sim.generation(), 14);
+    assert_eq!(sim.step_exponent(), 2);
+    assert_eq!(sim.engine().state_hash(), expected.engine().state_hash());
+}
+
+#[test]
+fn publishes_snapshots_while_running() {
+    let mut worker = Worker::spawn(simulation(), Speed::StepsPerFrame(5), true);
+    let start = Instant::now();
+    while worker.snapshot().generation < 50 {
+        assert!(start.elapsed() < Duration::from_secs(10), "no progress");
+        std::thread::sleep(Duration::from_millis(1));
+    }
+
+    worker.send(Command::Pause);
+    while worker.snapshot().running {
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    let snapshot = worker.snapshot().clone();
+    // Batches are whole frames and the board is packed like the renderer expects
+    assert_eq!(snapshot.generation % 5, 0);
+    assert_eq!(snapshot.words.len(), 100_usize.div_ceil(16) * 80);
+
+    let sim = worker.stop();
+    assert_eq!(sim.generation(), snapshot.generation);
+    assert_eq!(sim.engine().population(), snapshot.population);
+}
+
+#[test]
+fn reports_failed_commands() {
+    let config = Config { board_size : (100, 80), seed : Some(3), engine : EngineKind::HashLife, ..Config::default() };
+    let worker = Worker::spawn(Simulation::new(&config).unwrap(), Speed::default(), false);
+    worker.send(Command::SetRule("B2/S/C3".parse().unwrap()));
+    worker.send(Command::SetStepExponent(MAX_STEP_EXPONENT + 1));
+    worker.send(Command::SetStepExponent(1));
+    let start = Instant::now();
+    let mut errors = Vec::new();
+    while errors.len() < 2 {
+        assert!(start.elapsed() < Duration::from_secs(10), "no errors reported");
+        errors.extend(worker.errors());
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    assert!(errors[0].contains("Generations"), "{}", errors[0]);
+    assert!(errors[1].contains("exceed"), "{}", errors[1]);
+
+    let sim = worker.stop();
+    assert_eq!(sim.rule(), Rule::conway());
+    assert_eq!(sim.step_exponent(), 1);
+}
+
+#[test]
+fn pause_stops_long_advances() {
+    let mut worker = Worker::spawn(simulation(), Speed::default(), false);
+    worker.send(Command::Advance(1 << 40));
+    worker.send(Command::SetCell { col : 5, row : 6, value : true });
+    // The advance publishes its progress while it runs
+    let start = Instant::now();
+    while worker.snapshot().generation == 0 {
+        assert!(start.elapsed() < Duration::from_secs(10), "no progress");
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    worker.send(Command::Pause);
+    let sim = worker.stop();
+    assert!(start.elapsed() < Duration::from_secs(10));
+    assert!(sim.generation() < 1 << 40);
+    // Commands sent during the advance are applied after it stopped
+    assert!(sim.engine().get_cell(5, 6));
+}